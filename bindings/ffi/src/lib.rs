@@ -0,0 +1,3 @@
+//! Builds the C ABI of [`geonamescache::ffi`] as `libgeonamescache_ffi`, a shared and a
+//! static library. The declarations are in `include/geonamescache.h`.
+pub use geonamescache::ffi::*;
@@ -0,0 +1,3 @@
+//! Builds the `geonamescache` Python module defined by the `python` feature of the
+//! geonamescache crate. See `pyproject.toml`.
+pub use geonamescache::python::*;
@@ -0,0 +1,3 @@
+//! Builds the `geonamescache` package for JavaScript from the `wasm` feature of the
+//! geonamescache crate, with `wasm-pack build bindings/wasm`.
+pub use geonamescache::wasm::*;
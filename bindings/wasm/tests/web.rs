@@ -0,0 +1,68 @@
+//! Tests of the JavaScript API, run with `wasm-pack test --node bindings/wasm`, and with
+//! `--no-default-features` for a package without the bundled cities.
+#![cfg(target_arch = "wasm32")]
+
+use geonamescache_wasm::WasmGeonamescache;
+use serde_json::Value;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const CITIES_JSON: &str = r#"{
+    "3117735": {
+        "geonameid": 3117735, "name": "Madrid", "latitude": 40.4165,
+        "longitude": -3.70256, "countrycode": "ES", "population": 3255944,
+        "timezone": "Europe/Madrid", "admin1code": "29", "alternatenames": ["Madrit"]
+    }
+}"#;
+
+fn value(js: JsValue) -> Value {
+    serde_wasm_bindgen::from_value(js).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_countries() {
+    let gc = WasmGeonamescache::new();
+    assert_eq!(value(gc.get_country("DE").unwrap())["iso3"], "DEU");
+    assert!(gc.get_country("XX").unwrap().is_null());
+    assert_eq!(value(gc.get_countries().unwrap())["FR"]["name"], "France");
+    assert_eq!(value(gc.resolve_country("deu").unwrap())["iso"], "DE");
+}
+
+#[wasm_bindgen_test]
+fn test_country_mapper() {
+    let gc = WasmGeonamescache::new();
+    let mapper = gc.country_mapper("name", "iso3").unwrap();
+    assert_eq!(mapper.get("spain").as_deref(), Some("ESP"));
+    assert_eq!(mapper.get("Atlantis"), None);
+    assert!(gc.country_mapper("name", "nope").is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_with_cities_json() {
+    let gc = WasmGeonamescache::with_cities_json(CITIES_JSON).unwrap();
+    let cities = value(gc.get_cities_by_name("madrid").unwrap());
+    assert_eq!(cities[0]["geonameid"], 3117735);
+    let nearest = value(gc.nearest_city(40.42, -3.7).unwrap());
+    assert_eq!(nearest["name"], "Madrid");
+    assert!(gc.nearest_city(f64::NAN, -3.7).is_err());
+    assert!(gc.nearest_city(40.42, 200.0).is_err());
+    assert!(WasmGeonamescache::with_cities_json("[]").is_err());
+}
+
+#[cfg(feature = "embedded-cities")]
+#[wasm_bindgen_test]
+fn test_embedded_cities() {
+    let gc = WasmGeonamescache::new();
+    let cities = value(gc.search_cities("kiev").unwrap());
+    assert_eq!(cities[0]["geonameid"], 703448);
+}
+
+#[cfg(not(feature = "embedded-cities"))]
+#[wasm_bindgen_test]
+fn test_no_cities() {
+    let gc = WasmGeonamescache::new();
+    assert!(gc.get_cities_by_name("Madrid").is_err());
+    assert!(gc.search_cities("Madrid").is_err());
+    assert!(gc.nearest_city(40.42, -3.7).is_err());
+    assert!(gc.get_country("ES").is_ok());
+}
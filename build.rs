@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::{env, fs};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use self::geometry::{centroid, BoundingBox, Point};
+
+extern crate alloc;
+
+#[allow(dead_code)]
+#[path = "src/geometry.rs"]
+mod geometry;
+#[allow(dead_code)]
+#[path = "src/snapshot/write.rs"]
+mod write;
+
+#[derive(Deserialize)]
+struct City {
+    geonameid: u32,
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    countrycode: String,
+    population: u32,
+    timezone: String,
+    admin1code: String,
+    alternatenames: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Country {
+    geonameid: u32,
+    name: String,
+    iso: String,
+    iso3: String,
+    isonumeric: u32,
+    fips: String,
+    continentcode: String,
+    capital: String,
+    areakm2: u32,
+    population: u32,
+    tld: String,
+    currencycode: String,
+    currencyname: String,
+    phone: String,
+    languages: String,
+    neighbours: String,
+    bbox: Option<BoundingBox>,
+    centroid: Option<Point>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Timezone {
+    gmt_offset: i8,
+    dst_offset: i8,
+    time_zone_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlternateName {
+    name: String,
+    lang: String,
+    #[serde(default)]
+    is_preferred_name: bool,
+    #[serde(default)]
+    is_short_name: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Continent {
+    lng: String,
+    lat: String,
+    geoname_id: u32,
+    timezone: Timezone,
+    toponym_name: String,
+    ascii_name: String,
+    name: String,
+    continent_code: String,
+    population: u32,
+    #[serde(rename = "wikipediaURL")]
+    wikipedia_url: String,
+    alternate_names: Vec<AlternateName>,
+    cc2: Option<String>,
+    #[serde(default)]
+    bbox: BoundingBox,
+}
+
+#[derive(Deserialize)]
+struct UsState {
+    code: String,
+    name: String,
+    fips: String,
+    geonameid: u32,
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> BTreeMap<String, T> {
+    println!("cargo:rerun-if-changed={}", path);
+    let json =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("Failed to parse {}: {}", path, e))
+}
+
+fn bbox(b: &BoundingBox) -> String {
+    format!(
+        "BoundingBox {{ north: {:?}, south: {:?}, east: {:?}, west: {:?} }}",
+        b.north, b.south, b.east, b.west
+    )
+}
+
+fn point(p: &Point) -> String {
+    format!("Point {{ lat: {:?}, lon: {:?} }}", p.lat, p.lon)
+}
+
+fn option(value: Option<String>) -> String {
+    value.map_or("None".to_string(), |v| format!("Some({})", v))
+}
+
+/// Write a `static` array of the records, ordered by key, and a `const fn` mapping
+/// each key to its index in the array.
+fn write_table<T>(
+    out: &mut String,
+    records: &BTreeMap<String, T>,
+    (table, index, ty): (&str, &str, &str),
+    literal: impl Fn(&T) -> String,
+) {
+    writeln!(out, "pub static {}: [{}; {}] = [", table, ty, records.len()).unwrap();
+    for record in records.values() {
+        writeln!(out, "    {},", literal(record)).unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    writeln!(out, "const fn {}(key: &[u8]) -> Option<usize> {{", index).unwrap();
+    writeln!(out, "    match key {{").unwrap();
+    for (i, key) in records.keys().enumerate() {
+        writeln!(out, "        b{:?} => Some({}),", key, i).unwrap();
+    }
+    writeln!(out, "        _ => None,\n    }}\n}}\n").unwrap();
+}
+
+/// Fill in the geometry of countries that `countries.json` lacks, i.e. when it was
+/// generated without the country shapes, from the extent and mean position of their
+/// cities in `src/cities.json`, if there is one.
+fn fill_country_geometry(countries: &mut BTreeMap<String, Country>) {
+    if !Path::new("src/cities.json").exists() {
+        return;
+    }
+    let cities: BTreeMap<String, City> = read_json("src/cities.json");
+    let mut points: HashMap<&str, Vec<Point>> = HashMap::new();
+    for city in cities.values() {
+        points
+            .entry(city.countrycode.as_str())
+            .or_default()
+            .push(Point {
+                lat: city.latitude,
+                lon: city.longitude,
+            });
+    }
+
+    for country in countries.values_mut() {
+        let Some(points) = points.get(country.iso.as_str()) else {
+            continue;
+        };
+        if country.bbox.is_none() {
+            country.bbox = BoundingBox::from_points(points.iter().copied());
+        }
+        if country.centroid.is_none() {
+            country.centroid = centroid(points);
+        }
+    }
+}
+
+/// Generate `$OUT_DIR/tables.rs`, the static country, continent and US state tables.
+fn build_tables(out_dir: &Path) {
+    let mut countries: BTreeMap<String, Country> = read_json("src/countries.json");
+    fill_country_geometry(&mut countries);
+    let continents: BTreeMap<String, Continent> = read_json("src/continents.json");
+    let us_states: BTreeMap<String, UsState> = read_json("src/us_states.json");
+
+    let mut out = String::new();
+    write_table(
+        &mut out,
+        &countries,
+        ("COUNTRIES", "country_index", "StaticCountry"),
+        |c| {
+            format!(
+                "StaticCountry {{ geonameid: {}, name: {:?}, iso: {:?}, iso3: {:?}, \
+                 isonumeric: {}, fips: {:?}, continentcode: {:?}, capital: {:?}, \
+                 areakm2: {}, population: {}, tld: {:?}, currencycode: {:?}, \
+                 currencyname: {:?}, phone: {:?}, languages: {:?}, neighbours: {:?}, \
+                 bbox: {}, centroid: {} }}",
+                c.geonameid,
+                c.name,
+                c.iso,
+                c.iso3,
+                c.isonumeric,
+                c.fips,
+                c.continentcode,
+                c.capital,
+                c.areakm2,
+                c.population,
+                c.tld,
+                c.currencycode,
+                c.currencyname,
+                c.phone,
+                c.languages,
+                c.neighbours,
+                option(c.bbox.as_ref().map(bbox)),
+                option(c.centroid.as_ref().map(point)),
+            )
+        },
+    );
+    write_table(
+        &mut out,
+        &continents,
+        ("CONTINENTS", "continent_index", "StaticContinent"),
+        |c| {
+            let cc2: Vec<&str> = c.cc2.iter().flat_map(|cc2| cc2.split(',')).collect();
+            let alternate_names: Vec<String> = c
+                .alternate_names
+                .iter()
+                .map(|n| {
+                    format!(
+                        "StaticAlternateName {{ name: {:?}, lang: {:?}, \
+                         is_preferred_name: {}, is_short_name: {} }}",
+                        n.name, n.lang, n.is_preferred_name, n.is_short_name
+                    )
+                })
+                .collect();
+            format!(
+                "StaticContinent {{ geonameid: {}, code: {:?}, name: {:?}, \
+                 toponym_name: {:?}, ascii_name: {:?}, population: {}, \
+                 wikipedia_url: {:?}, cc2: &{:?}, lat: {:?}, lng: {:?}, centroid: {}, \
+                 bbox: {}, timezone: StaticTimezone {{ gmt_offset: {}, dst_offset: {}, \
+                 time_zone_id: {:?} }}, alternate_names: &[{}] }}",
+                c.geoname_id,
+                c.continent_code,
+                c.name,
+                c.toponym_name,
+                c.ascii_name,
+                c.population,
+                c.wikipedia_url,
+                cc2,
+                c.lat,
+                c.lng,
+                point(&Point {
+                    lat: c.lat.parse().unwrap_or_default(),
+                    lon: c.lng.parse().unwrap_or_default(),
+                }),
+                bbox(&c.bbox),
+                c.timezone.gmt_offset,
+                c.timezone.dst_offset,
+                c.timezone.time_zone_id,
+                alternate_names.join(", "),
+            )
+        },
+    );
+    write_table(
+        &mut out,
+        &us_states,
+        ("US_STATES", "us_state_index", "StaticUsState"),
+        |s| {
+            format!(
+                "StaticUsState {{ code: {:?}, name: {:?}, fips: {:?}, geonameid: {} }}",
+                s.code, s.name, s.fips, s.geonameid
+            )
+        },
+    );
+    fs::write(out_dir.join("tables.rs"), out).expect("Failed to write tables.rs");
+}
+
+/// Encode `src/cities.json` as `$OUT_DIR/cities.snapshot`.
+fn build_snapshot(out_dir: &Path) {
+    println!("cargo:rerun-if-changed=src/cities.json");
+    let json = fs::read_to_string("src/cities.json").expect("Failed to read src/cities.json");
+    let cities: HashMap<String, City> =
+        serde_json::from_str(&json).expect("Failed to parse src/cities.json");
+
+    let records = cities.values().map(|c| write::Record {
+        geonameid: c.geonameid,
+        name: &c.name,
+        latitude: c.latitude,
+        longitude: c.longitude,
+        countrycode: &c.countrycode,
+        population: c.population,
+        timezone: &c.timezone,
+        admin1code: &c.admin1code,
+        alternatenames: c.alternatenames.iter().map(String::as_str).collect(),
+        localized_names: Vec::new(),
+    });
+    fs::write(out_dir.join("cities.snapshot"), write::write(records))
+        .expect("Failed to write cities.snapshot");
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/snapshot/write.rs");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+    build_tables(out_dir);
+    if env::var_os("CARGO_FEATURE_SNAPSHOT").is_some() {
+        build_snapshot(out_dir);
+    }
+}
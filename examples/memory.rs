@@ -0,0 +1,61 @@
+//! Print the heap memory used by a city dataset, as `Geonamescache` stores it and as
+//! `CompactCities`, as a Markdown table.
+//!
+//! Run with `cargo run --release --example memory [FILE...]`. Without arguments the
+//! bundled dataset is measured, otherwise each `cities.json` file given.
+use std::collections::HashMap;
+use std::error::Error;
+use std::mem::size_of;
+use std::{env, fs};
+
+use geonamescache::{City, CompactCities, Geonamescache};
+
+/// Approximate heap size of a `HashMap<String, City>`: the table itself plus the
+/// strings and vectors its entries own.
+fn hashmap_heap_size(cities: &HashMap<String, City>) -> usize {
+    let table = cities.capacity() * (size_of::<(String, City)>() + 1);
+    let owned: usize = cities
+        .iter()
+        .map(|(k, c)| {
+            k.capacity()
+                + c.name.capacity()
+                + c.countrycode.capacity()
+                + c.timezone.capacity()
+                + c.admin1code.capacity()
+                + c.alternatenames.capacity() * size_of::<String>()
+                + c.alternatenames.iter().map(|n| n.capacity()).sum::<usize>()
+        })
+        .sum();
+    table + owned
+}
+
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn row(dataset: &str, cities: &HashMap<String, City>) {
+    let hashmap = hashmap_heap_size(cities);
+    let compact = CompactCities::from_cities(cities.values()).heap_size();
+    println!(
+        "| {} | {} | {} | {} ({:.0}%) |",
+        dataset,
+        cities.len(),
+        mib(hashmap),
+        mib(compact),
+        100.0 * compact as f64 / hashmap as f64
+    );
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    println!("| dataset | cities | `HashMap<String, City>` | `CompactCities` |");
+    println!("|---------|--------|-------------------------|-----------------|");
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        row("bundled", Geonamescache::new().get_cities());
+    }
+    for path in paths {
+        let cities: HashMap<String, City> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        row(&path, &cities);
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+/*
+ * C interface to geonamescache, built from the `ffi` feature by the bindings/ffi
+ * crate:
+ *
+ *     cargo build --release -p geonamescache-ffi
+ *
+ * and linked against target/release/libgeonamescache_ffi.{so,dylib,a}.
+ *
+ * Ownership: every pointer returned by a gnc_* function is owned by the caller and
+ * must be released with the matching gnc_*_free function. Pointers passed in are only
+ * borrowed for the duration of the call. Functions return NULL when there is no
+ * result, when an argument is NULL, when a string isn't valid UTF-8 or when
+ * coordinates aren't finite or are outside +-90 latitude and +-180 longitude. The
+ * string fields of a returned gnc_city are never NULL; interior NULs are dropped.
+ *
+ * A cache may be shared between threads. Strings are UTF-8.
+ */
+#ifndef GEONAMESCACHE_H
+#define GEONAMESCACHE_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Geonamescache gnc_cache;
+typedef struct GncCountryMapper gnc_country_mapper;
+
+typedef struct {
+    uint32_t geonameid;
+    char *name;
+    double latitude;
+    double longitude;
+    char *countrycode;
+    uint32_t population;
+    char *timezone;
+    char *admin1code;
+} gnc_city;
+
+/* Create a cache. Datasets are loaded on first use. Free with gnc_free. */
+gnc_cache *gnc_new(void);
+void gnc_free(gnc_cache *gc);
+
+/* ISO code of the country a name, alias or ISO/FIPS code refers to, e.g. "Germany"
+ * or "DEU" give "DE". Free with gnc_string_free. */
+char *gnc_resolve_country(const gnc_cache *gc, const char *query);
+
+/* Map between two country fields given by name, e.g. "name" and "iso3". Returns NULL
+ * for unknown fields. The mapper doesn't borrow the cache. Free with
+ * gnc_country_mapper_free. */
+gnc_country_mapper *gnc_country_mapper_new(const gnc_cache *gc, const char *from,
+                                           const char *to);
+/* The value mapped to key (case-insensitive). Free with gnc_string_free. */
+char *gnc_country_mapper_get(const gnc_country_mapper *mapper, const char *key);
+void gnc_country_mapper_free(gnc_country_mapper *mapper);
+
+/* Free the returned cities with gnc_city_free, which also frees their strings.
+ * gnc_nearest_city returns NULL for invalid coordinates, e.g. NaN. */
+gnc_city *gnc_city_by_id(const gnc_cache *gc, uint32_t geonameid);
+gnc_city *gnc_nearest_city(const gnc_cache *gc, double lat, double lon);
+void gnc_city_free(gnc_city *city);
+
+void gnc_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GEONAMESCACHE_H */
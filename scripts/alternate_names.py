@@ -0,0 +1,50 @@
+#!/usr/bin/env python
+# -*- coding: utf-8 -*-
+# Extract the alternate names of the bundled countries and cities in major languages
+# and store them in a json file keyed by geonameid. Must run after countries.py and
+# cities.py.
+import csv
+import json
+import sys
+
+LANGUAGES = {
+    "ar", "bn", "de", "en", "es", "fa", "fr", "hi", "id", "it", "ja", "ko", "nl",
+    "pl", "pt", "ru", "sv", "th", "tr", "uk", "ur", "vi", "zh",
+}
+
+with open("src/countries.json", "r", encoding="utf-8") as f:
+    geonameids = {c["geonameid"] for c in json.load(f).values()}
+with open("src/cities.json", "r", encoding="utf-8") as f:
+    geonameids |= {c["geonameid"] for c in json.load(f).values()}
+
+csv.field_size_limit(sys.maxsize)
+names = {}
+with open("data/alternateNamesV2.txt", "r", encoding="utf-8") as f:
+    for record in csv.reader(f, "excel-tab", quoting=csv.QUOTE_NONE):
+        (
+            alternatenameid,
+            geonameid,
+            isolanguage,
+            alternatename,
+            ispreferredname,
+            isshortname,
+            iscolloquial,
+            ishistoric,
+            *_,
+        ) = record
+
+        geonameid = int(geonameid)
+        if geonameid not in geonameids or isolanguage not in LANGUAGES:
+            continue
+        if iscolloquial == "1" or ishistoric == "1":
+            continue
+
+        name = {"name": alternatename, "lang": isolanguage}
+        if ispreferredname == "1":
+            name["isPreferredName"] = True
+        if isshortname == "1":
+            name["isShortName"] = True
+        names.setdefault(geonameid, []).append(name)
+
+with open("src/alternate_names.json", "w", encoding="utf-8") as f:
+    json.dump(names, f, separators=(",", ":"), ensure_ascii=False)
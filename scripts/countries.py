@@ -2,6 +2,50 @@
 # -*- coding: utf-8 -*-
 import csv
 import json
+import os
+
+# Bounding boxes and centroids come from the GeoNames country shapes when they have
+# been downloaded (`make boundaries`). Otherwise build.rs approximates them from the
+# cities.
+geometry = {}
+
+
+def country_geometry(geom):
+    """Bounding box and centroid of a shape. The box crosses the antimeridian (west >
+    east) when that makes it narrower, e.g. for Fiji or Russia."""
+    polygons = getattr(geom, "geoms", [geom])
+    lons = sorted(x for p in polygons for x, _ in p.exterior.coords)
+    _, south, _, north = geom.bounds
+
+    # Leave out the widest gap between two longitudes, which is usually the one across
+    # the antimeridian.
+    gap, west, east = lons[0] + 360 - lons[-1], lons[0], lons[-1]
+    for a, b in zip(lons, lons[1:]):
+        if b - a > gap:
+            gap, west, east = b - a, b, a
+
+    centroid = geom.centroid
+    if west > east:
+        shifted = transform(
+            lambda xs, ys: (tuple(x + 360 if x < west else x for x in xs), ys), geom
+        )
+        centroid = shifted.centroid
+    lon = centroid.x - 360 if centroid.x > 180 else centroid.x
+    return {
+        "bbox": {"north": north, "south": south, "east": east, "west": west},
+        "centroid": {"lat": centroid.y, "lon": lon},
+    }
+
+
+if os.path.exists("data/shapes_simplified_low.json"):
+    from shapely.geometry import shape
+    from shapely.ops import transform
+
+    with open("data/shapes_simplified_low.json", "r", encoding="utf-8") as f:
+        shapes = csv.reader(f, "excel-tab")
+        next(shapes)
+        for geonameid, geojson in shapes:
+            geometry[geonameid] = country_geometry(shape(json.loads(geojson)))
 
 fcsv = open("data/countryInfo.txt", "r", encoding="utf-8")
 reader = csv.reader(fcsv, "excel-tab")
@@ -52,6 +96,7 @@ for record in reader:
         "postalcoderegex": postalcoderegex,
         "languages": languages,
         "neighbours": neighbours,
+        **geometry.get(geonameid, {}),
     }
 
 with open("src/countries.json", "w", encoding="utf-8") as f:
@@ -0,0 +1,31 @@
+#!/usr/bin/env python
+# -*- coding: utf-8 -*-
+# Store the GeoNames simplified country shapes as MultiPolygon coordinates keyed
+# by ISO code.
+import csv
+import json
+
+iso_by_geonameid = {}
+with open("data/countryInfo.txt", "r", encoding="utf-8") as f:
+    for record in csv.reader(f, "excel-tab"):
+        if record[0].startswith("#") or not record[16]:
+            continue
+        iso_by_geonameid[record[16]] = record[0]
+
+boundaries = {}
+with open("data/shapes_simplified_low.json", "r", encoding="utf-8") as f:
+    reader = csv.reader(f, "excel-tab")
+    headers = next(reader)
+    for geonameid, geojson in reader:
+        iso = iso_by_geonameid.get(geonameid)
+        if iso is None:
+            continue
+
+        geom = json.loads(geojson)
+        coords = geom["coordinates"]
+        if geom["type"] == "Polygon":
+            coords = [coords]
+        boundaries[iso] = coords
+
+with open("src/country_boundaries.json", "w", encoding="utf-8") as f:
+    json.dump(boundaries, f, separators=(",", ":"), ensure_ascii=False)
@@ -0,0 +1,27 @@
+#!/usr/bin/env python
+# -*- coding: utf-8 -*-
+# Simplify the timezone-boundary-builder polygons and store them as MultiPolygon
+# coordinates keyed by IANA timezone id.
+import json
+
+from shapely.geometry import mapping, shape
+
+# Tolerance in degrees, roughly 1 km at the equator.
+TOLERANCE = 0.01
+
+with open("data/combined.json", "r", encoding="utf-8") as f:
+    features = json.load(f)["features"]
+
+timezones = {}
+for feature in features:
+    geom = shape(feature["geometry"]).simplify(TOLERANCE, preserve_topology=True)
+    coords = mapping(geom)["coordinates"]
+    if geom.geom_type == "Polygon":
+        coords = [coords]
+    timezones[feature["properties"]["tzid"]] = [
+        [[[round(x, 4), round(y, 4)] for x, y in ring] for ring in polygon]
+        for polygon in coords
+    ]
+
+with open("src/timezones.json", "w", encoding="utf-8") as f:
+    json.dump(timezones, f, separators=(",", ":"), ensure_ascii=False)
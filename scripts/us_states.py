@@ -0,0 +1,29 @@
+#!/usr/bin/env python
+# coding: utf-8
+# US states (and DC) keyed by postal code: names and geonameids from the GeoNames
+# admin1 codes, FIPS codes from the census county list.
+import csv
+import json
+
+fips = {}
+with open("data/us_counties.txt", "r", encoding="utf-8") as f:
+    r = csv.reader(f)
+    headers = next(r)
+    for line in r:
+        fips[line[0]] = line[1]
+
+states = {}
+with open("data/admin1CodesASCII.txt", "r", encoding="utf-8") as f:
+    for code, name, asciiname, geonameid in csv.reader(f, "excel-tab"):
+        country, _, state = code.partition(".")
+        if country != "US" or state not in fips:
+            continue
+        states[state] = {
+            "code": state,
+            "name": name,
+            "fips": fips[state],
+            "geonameid": int(geonameid),
+        }
+
+with open("src/us_states.json", "w", encoding="utf-8") as f:
+    json.dump(states, f, separators=(",", ":"), ensure_ascii=False, sort_keys=True)
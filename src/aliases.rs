@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::data::COUNTRY_NAMES;
+
+/// Alternative spellings of country names: the built-in [`COUNTRY_NAMES`] plus any
+/// registered at runtime.
+#[derive(Debug, Default)]
+pub(crate) struct CountryAliases {
+    extra: RwLock<HashMap<String, String>>,
+}
+
+impl CountryAliases {
+    pub fn insert(&self, alias: &str, name: &str) {
+        let mut extra = self.extra.write().unwrap();
+        extra.insert(alias.to_lowercase(), name.to_string());
+    }
+
+    /// Load a JSON object mapping aliases to country names, e.g.
+    /// `{"Türkiye": "Turkey"}`.
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let reader = BufReader::new(File::open(path)?);
+        let aliases: HashMap<String, String> = serde_json::from_reader(reader)?;
+        for (alias, name) in aliases {
+            self.insert(&alias, &name);
+        }
+        Ok(())
+    }
+
+    /// Lowercase name of the country `name` is an alias of, if any. Registered
+    /// aliases may point to a built-in alias (e.g. "U.S.A." to "USA").
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        let extra = self.extra.read().unwrap();
+        resolve(&extra, &name.to_lowercase())
+    }
+
+    /// All the aliases, lowercased, with the lowercase name of their country.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let extra = self.extra.read().unwrap();
+        COUNTRY_NAMES
+            .keys()
+            .map(|k| k.to_lowercase())
+            .chain(extra.keys().cloned())
+            .filter_map(|alias| {
+                let name = resolve(&extra, &alias)?;
+                Some((alias, name))
+            })
+            .collect()
+    }
+}
+
+fn resolve(extra: &HashMap<String, String>, name: &str) -> Option<String> {
+    let registered = extra.get(name).map(|n| n.to_lowercase());
+
+    let key = registered.as_deref().unwrap_or(name);
+    match COUNTRY_NAMES.get(key) {
+        Some(builtin) => Some(builtin.to_lowercase()),
+        None => registered,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_builtin() {
+        let aliases = CountryAliases::default();
+        assert_eq!(aliases.resolve("Burma"), Some("myanmar".into()));
+        assert_eq!(aliases.resolve("VIET NAM"), Some("vietnam".into()));
+        assert_eq!(aliases.resolve("France"), None);
+    }
+
+    #[test]
+    fn test_resolve_registered() {
+        let aliases = CountryAliases::default();
+        aliases.insert("Türkiye", "Turkey");
+        aliases.insert("U.S.A.", "USA");
+
+        assert_eq!(aliases.resolve("türkiye"), Some("turkey".into()));
+        assert_eq!(aliases.resolve("u.s.a."), Some("united states".into()));
+    }
+
+    #[test]
+    fn test_entries() {
+        let aliases = CountryAliases::default();
+        aliases.insert("Türkiye", "Turkey");
+        let entries = aliases.entries();
+
+        assert!(entries.contains(&("türkiye".into(), "turkey".into())));
+        assert!(entries.contains(&("burma".into(), "myanmar".into())));
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!(
+            "geonamescache_test_load_{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, r#"{"Eswatini (Swaziland)": "Swaziland"}"#).unwrap();
+
+        let aliases = CountryAliases::default();
+        aliases.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            aliases.resolve("Eswatini (Swaziland)"),
+            Some("swaziland".into())
+        );
+        assert!(aliases.load("does/not/exist.json").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! `geonamescache-server`, a JSON lookup service built with the `server` feature.
+//!
+//! Usage: `geonamescache-server [ADDR]`, listening on `127.0.0.1:8080` by default.
+//! Every request is served from the shared [`Geonamescache::global`] instance. Its
+//! datasets are loaded in the background at startup: `/ready` answers 503 until they
+//! are, while `/health` only reports that the process is up.
+use std::collections::BTreeMap;
+use std::env;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use geonamescache::Geonamescache;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Server};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+/// Set once every dataset is loaded.
+static READY: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok<T: Serialize + ?Sized>(value: &T) -> Response {
+        match serde_json::to_value(value) {
+            Ok(body) => Response { status: 200, body },
+            Err(e) => Response::error(500, e.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// Decode `%XX` escapes and `+` as a space. Malformed escapes are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Split a request URL into its decoded path and query parameters, in order.
+fn parse_url(url: &str) -> (String, Vec<(String, String)>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect();
+    (percent_decode(path), params)
+}
+
+fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn coordinate(params: &[(String, String)], key: &str, max: f64) -> Result<f64, Response> {
+    let value = param(params, key)
+        .ok_or_else(|| Response::error(400, format!("missing parameter {:?}", key)))?;
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.abs() <= max)
+        .ok_or_else(|| {
+            Response::error(
+                400,
+                format!("{} must be a number between -{} and {}", key, max, max),
+            )
+        })
+}
+
+fn search_cities(gc: &Geonamescache, params: &[(String, String)]) -> Response {
+    let Some(query) = param(params, "q").filter(|q| !q.trim().is_empty()) else {
+        return Response::error(400, "missing parameter \"q\"");
+    };
+    let limit = match param(params, "limit").map(str::parse::<usize>) {
+        None => usize::MAX,
+        Some(Ok(limit)) if limit > 0 => limit,
+        Some(_) => return Response::error(400, "limit must be a positive integer"),
+    };
+    let mut cities = gc.search_cities(query);
+    cities.truncate(limit);
+    Response::ok(&cities)
+}
+
+fn nearest_city(gc: &Geonamescache, params: &[(String, String)]) -> Response {
+    let coordinates = coordinate(params, "lat", 90.0)
+        .and_then(|lat| Ok((lat, coordinate(params, "lon", 180.0)?)));
+    let (lat, lon) = match coordinates {
+        Ok(c) => c,
+        Err(response) => return response,
+    };
+    match gc.nearest_city_with_distance(lat, lon) {
+        Some(nearest) => Response::ok(&nearest),
+        None => Response::error(404, "no cities loaded"),
+    }
+}
+
+/// The values given as `value` parameters mapped from one country field to another,
+/// or the whole mapping if there are none. Keys shared by several countries, e.g. a
+/// currency code, are rejected rather than mapped to any one of them.
+fn map_country(gc: &Geonamescache, params: &[(String, String)]) -> Response {
+    let from = param(params, "from").unwrap_or("name");
+    let to = param(params, "to").unwrap_or("iso");
+    let mapper = match gc.country_field_mapper(from, to) {
+        Ok(mapper) => mapper,
+        Err(e) => return Response::error(400, e.to_string()),
+    };
+    let values: Vec<&str> = params
+        .iter()
+        .filter(|(k, _)| k == "value")
+        .map(|(_, v)| v.as_str())
+        .collect();
+    // Countries without a value for `from` share the empty key, and are left out.
+    let shared: Vec<&str> = if values.is_empty() {
+        mapper.collisions().filter(|k| !k.is_empty()).collect()
+    } else {
+        values
+            .iter()
+            .copied()
+            .filter(|v| mapper.get_all(v).nth(1).is_some())
+            .collect()
+    };
+    if !shared.is_empty() {
+        return Response::error(
+            400,
+            format!(
+                "{} is shared by several countries: {}",
+                from,
+                shared.join(", ")
+            ),
+        );
+    }
+    if values.is_empty() {
+        let mapping: BTreeMap<_, _> = gc
+            .get_countries()
+            .values()
+            .filter_map(|c| Some((c.field(from)?, c.field(to)?)))
+            .filter(|(k, _)| !k.is_empty())
+            .collect();
+        return Response::ok(&mapping);
+    }
+    let mapping: BTreeMap<&str, Option<&str>> =
+        values.into_iter().map(|v| (v, mapper.get(v))).collect();
+    Response::ok(&mapping)
+}
+
+fn route(gc: &Geonamescache, ready: bool, method: &Method, url: &str) -> Response {
+    if !matches!(method, Method::Get | Method::Head) {
+        return Response::error(405, format!("method {} not allowed", method));
+    }
+    let (path, params) = parse_url(url);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["health"] => Response::ok(&json!({ "status": "ok" })),
+        ["ready"] if ready => Response::ok(&json!({ "status": "ready" })),
+        ["ready"] => Response {
+            status: 503,
+            body: json!({ "status": "loading" }),
+        },
+        ["countries", iso] => match gc.get_countries().get(&iso.to_uppercase()) {
+            Some(country) => Response::ok(country),
+            None => Response::error(404, format!("no country with ISO code {:?}", iso)),
+        },
+        ["cities", "search"] => search_cities(gc, &params),
+        ["cities", "nearest"] => nearest_city(gc, &params),
+        ["map", "country"] => map_country(gc, &params),
+        _ => Response::error(404, format!("no route for {}", path)),
+    }
+}
+
+fn respond(request: Request) {
+    let ready = READY.load(Ordering::Acquire);
+    let response = route(
+        Geonamescache::global(),
+        ready,
+        request.method(),
+        request.url(),
+    );
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let http_response = tiny_http::Response::from_string(response.body.to_string())
+        .with_status_code(response.status)
+        .with_header(content_type);
+    if let Err(e) = request.respond(http_response) {
+        eprintln!("geonamescache-server: failed to respond: {}", e);
+    }
+}
+
+fn main() -> ExitCode {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let server = match Server::http(&addr) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("geonamescache-server: cannot listen on {}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    thread::spawn(|| {
+        Geonamescache::global().preload_all();
+        READY.store(true, Ordering::Release);
+    });
+    eprintln!("geonamescache-server: listening on http://{}", addr);
+
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            thread::spawn(move || server.incoming_requests().for_each(respond))
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get(url: &str) -> Response {
+        route(Geonamescache::global(), true, &Method::Get, url)
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let (path, params) = parse_url("/cities/search?q=S%C3%A3o+Paulo&limit=2&flag");
+        assert_eq!(path, "/cities/search");
+        assert_eq!(param(&params, "q"), Some("São Paulo"));
+        assert_eq!(param(&params, "limit"), Some("2"));
+        assert_eq!(param(&params, "flag"), Some(""));
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[test]
+    fn test_health() {
+        assert_eq!(get("/health").status, 200);
+        assert_eq!(get("/ready").status, 200);
+        let loading = route(Geonamescache::global(), false, &Method::Get, "/ready");
+        assert_eq!(loading.status, 503);
+        assert_eq!(get("/nope").status, 404);
+        let post = route(Geonamescache::global(), true, &Method::Post, "/health");
+        assert_eq!(post.status, 405);
+    }
+
+    #[test]
+    fn test_countries() {
+        let response = get("/countries/fr");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["iso3"], "FRA");
+        assert_eq!(get("/countries/XX").status, 404);
+    }
+
+    #[test]
+    fn test_cities() {
+        let response = get("/cities/search?q=kiev");
+        assert_eq!(response.body[0]["geonameid"], 703448);
+
+        let response = get("/cities/search?q=madrid&limit=1");
+        assert_eq!(response.body.as_array().unwrap().len(), 1);
+        assert_eq!(response.body[0]["countrycode"], "ES");
+        assert_eq!(get("/cities/search").status, 400);
+        assert_eq!(get("/cities/search?q=madrid&limit=0").status, 400);
+        assert_eq!(get("/cities/search?q=madrid&limit=-1").status, 400);
+
+        let response = get("/cities/nearest?lat=40.42&lon=-3.7");
+        assert_eq!(response.body["geonameid"], 3117735);
+        assert!(response.body["distance_km"].as_f64().unwrap() < 1.0);
+        assert_eq!(get("/cities/nearest?lat=91&lon=0").status, 400);
+        assert_eq!(get("/cities/nearest?lat=0").status, 400);
+    }
+
+    #[test]
+    fn test_map_country() {
+        let response = get("/map/country?from=name&to=iso3&value=Spain&value=Atlantis");
+        assert_eq!(response.body, json!({ "Spain": "ESP", "Atlantis": null }));
+
+        let response = get("/map/country?from=iso&to=iso3");
+        assert_eq!(response.body["DE"], "DEU");
+        assert_eq!(get("/map/country?from=name&to=nope").status, 400);
+
+        let response = get("/map/country?from=currencycode&to=iso");
+        assert_eq!(response.status, 400);
+        assert!(response.body["error"].as_str().unwrap().contains("EUR"));
+        assert_eq!(get("/map/country?from=currencycode&value=EUR").status, 400);
+        let response = get("/map/country?from=currencycode&to=iso&value=JPY");
+        assert_eq!(response.body, json!({ "JPY": "JP" }));
+        let response = get("/map/country?from=fips&to=iso");
+        assert_eq!(response.body["GM"], "DE");
+        assert!(response.body.get("").is_none());
+    }
+}
@@ -0,0 +1,390 @@
+//! The `enrich` command: append country or city columns to a CSV or TSV file.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use csv::StringRecord;
+use geonamescache::{City, CityHints, Country, Geonamescache};
+
+use crate::NotFound;
+
+/// Columns from the matched city, when resolving cities.
+const CITY_FIELDS: &[&str] = &[
+    "geonameid",
+    "name",
+    "latitude",
+    "longitude",
+    "countrycode",
+    "population",
+    "timezone",
+    "admin1code",
+];
+
+/// Columns that aren't a field of a record.
+const DERIVED_FIELDS: &[&str] = &["country", "continent"];
+
+#[derive(Args)]
+pub struct EnrichArgs {
+    /// CSV or TSV file to read, or standard input if omitted
+    input: Option<PathBuf>,
+    /// Header of the column holding the country or city
+    #[arg(long, short)]
+    column: String,
+    /// What the column holds
+    #[arg(long, value_enum, default_value_t = Kind::Country)]
+    kind: Kind,
+    /// Match countries exactly on this field, e.g. `iso3` or `fips`, instead of
+    /// resolving names, codes and aliases
+    #[arg(long)]
+    from: Option<String>,
+    /// Column with the country of each city, used to choose between cities with the
+    /// same name
+    #[arg(long)]
+    country_column: Option<String>,
+    /// Column with the US state or admin1 code of each city, used to choose between
+    /// cities with the same name
+    #[arg(long)]
+    region_column: Option<String>,
+    /// Columns to append, separated by commas
+    ///
+    /// Any country field (see `map`), `country` for the country name and `continent`
+    /// for the continent name. With `--kind city`, the city fields (geonameid, name,
+    /// latitude, longitude, countrycode, population, timezone, admin1code) are taken
+    /// from the city and the others from its country.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "iso3,continent,population"
+    )]
+    add: Vec<String>,
+    /// Read and write tab-separated values. Implied by a `.tsv` input file
+    #[arg(long)]
+    tsv: bool,
+    /// Write the enriched file here instead of standard output
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// Write the rows that failed to resolve here, as CSV with the columns `line`,
+    /// `value` and `suggestions`, instead of listing them on standard error
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+enum Kind {
+    Country,
+    City,
+}
+
+/// What a row resolved to. A city always comes with its country.
+#[derive(Clone, Copy, Default)]
+struct Place<'a> {
+    city: Option<&'a City>,
+    country: Option<&'a Country>,
+}
+
+impl Place<'_> {
+    fn is_resolved(&self) -> bool {
+        self.country.is_some()
+    }
+}
+
+/// Closest country names of each unresolved value.
+type Suggestions = HashMap<String, Vec<String>>;
+
+/// A row that failed to resolve.
+struct Failure {
+    line: u64,
+    value: String,
+    suggestions: Vec<String>,
+}
+
+fn column_index(headers: &StringRecord, name: &str) -> Result<usize, String> {
+    headers.iter().position(|h| h == name).ok_or_else(|| {
+        let headers: Vec<&str> = headers.iter().collect();
+        format!(
+            "no column named {:?}, the columns are: {}",
+            name,
+            headers.join(", ")
+        )
+    })
+}
+
+fn check_fields(fields: &[String], kind: Kind) -> Result<(), String> {
+    let city_fields = if kind == Kind::City { CITY_FIELDS } else { &[] };
+    let accepted = || {
+        city_fields
+            .iter()
+            .chain(DERIVED_FIELDS)
+            .chain(Country::FIELDS)
+    };
+    match fields.iter().find(|f| !accepted().any(|a| a == f)) {
+        Some(field) => Err(format!(
+            "unknown column {:?} for --add, expected one of {}",
+            field,
+            accepted().copied().collect::<Vec<_>>().join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+fn city_field(city: &City, field: &str) -> Option<String> {
+    Some(match field {
+        "geonameid" => city.geonameid.to_string(),
+        "name" => city.name.clone(),
+        "latitude" => city.latitude.to_string(),
+        "longitude" => city.longitude.to_string(),
+        "countrycode" => city.countrycode.clone(),
+        "population" => city.population.to_string(),
+        "timezone" => city.timezone.clone(),
+        "admin1code" => city.admin1code.clone(),
+        _ => return None,
+    })
+}
+
+/// Value of an output column, empty if the row didn't resolve.
+fn field(gc: &Geonamescache, place: Place, field: &str) -> String {
+    if let Some(value) = place.city.and_then(|c| city_field(c, field)) {
+        return value;
+    }
+    let Some(country) = place.country else {
+        return String::new();
+    };
+    match field {
+        "country" => country.name.clone(),
+        "continent" => gc
+            .continent_of_country(&country.iso)
+            .map_or_else(String::new, |c| c.name.clone()),
+        _ => country.field(field).unwrap_or_default().into_owned(),
+    }
+}
+
+/// Resolve each row's country, with suggestions for the values that didn't match.
+fn resolve_countries<'a>(
+    gc: &'a Geonamescache,
+    values: &[&str],
+    from: Option<&str>,
+) -> Result<(Vec<Place<'a>>, Suggestions), Box<dyn Error>> {
+    let place = |country| Place {
+        city: None,
+        country,
+    };
+    match from {
+        Some(from) => {
+            let mapper = gc.country_field_mapper(from, "iso")?;
+            let places = values
+                .iter()
+                .map(|v| {
+                    place(
+                        mapper
+                            .get(v.trim())
+                            .and_then(|iso| gc.get_countries().get(iso)),
+                    )
+                })
+                .collect();
+            Ok((places, Suggestions::new()))
+        }
+        None => {
+            let batch = gc.resolve_countries(values);
+            let unmatched = batch
+                .unmatched
+                .into_iter()
+                .map(|u| (u.value, u.suggestions))
+                .collect();
+            Ok((batch.countries.into_iter().map(place).collect(), unmatched))
+        }
+    }
+}
+
+/// The most likely city of each row, given the hint columns.
+fn resolve_cities<'a>(
+    gc: &'a Geonamescache,
+    records: &[StringRecord],
+    column: usize,
+    country_column: Option<usize>,
+    region_column: Option<usize>,
+) -> Vec<Place<'a>> {
+    let hint = |record: &StringRecord, column: Option<usize>| {
+        column
+            .and_then(|i| record.get(i))
+            .filter(|v| !v.trim().is_empty())
+            .map(str::to_string)
+    };
+    records
+        .iter()
+        .map(|record| {
+            let hints = CityHints {
+                country: hint(record, country_column),
+                admin1: hint(record, region_column),
+                ..CityHints::default()
+            };
+            let city = gc
+                .disambiguate_city(&record[column], &hints)
+                .first()
+                .map(|c| c.city);
+            Place {
+                city,
+                country: city.and_then(|c| gc.get_countries().get(&c.countrycode)),
+            }
+        })
+        .collect()
+}
+
+fn write_report(failures: &[Failure], path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["line", "value", "suggestions"])?;
+    for f in failures {
+        writer.write_record([&f.line.to_string(), &f.value, &f.suggestions.join("; ")])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn run(gc: &Geonamescache, args: EnrichArgs) -> Result<(), Box<dyn Error>> {
+    check_fields(&args.add, args.kind)?;
+    if args.kind == Kind::City && args.from.is_some() {
+        return Err("--from only applies to --kind country".into());
+    }
+    if args.kind == Kind::Country && (args.country_column.is_some() || args.region_column.is_some())
+    {
+        return Err("--country-column and --region-column only apply to --kind city".into());
+    }
+
+    let tsv = args.tsv
+        || args
+            .input
+            .as_ref()
+            .is_some_and(|p| p.extension().is_some_and(|e| e == "tsv"));
+    let delimiter = if tsv { b'\t' } else { b',' };
+
+    let input: Box<dyn Read> = match &args.input {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(input);
+    let headers = reader.headers()?.clone();
+    let column = column_index(&headers, &args.column)?;
+    let country_column = args
+        .country_column
+        .as_deref()
+        .map(|c| column_index(&headers, c))
+        .transpose()?;
+    let region_column = args
+        .region_column
+        .as_deref()
+        .map(|c| column_index(&headers, c))
+        .transpose()?;
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+
+    let (places, suggestions) = match args.kind {
+        Kind::Country => {
+            let values: Vec<&str> = records.iter().map(|r| &r[column]).collect();
+            resolve_countries(gc, &values, args.from.as_deref())?
+        }
+        Kind::City => (
+            resolve_cities(gc, &records, column, country_column, region_column),
+            Suggestions::new(),
+        ),
+    };
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(output);
+    let mut header = headers.clone();
+    header.extend(&args.add);
+    writer.write_record(&header)?;
+
+    let mut failures = Vec::new();
+    for (record, place) in records.iter().zip(&places) {
+        let mut row = record.clone();
+        for name in &args.add {
+            row.push_field(&field(gc, *place, name));
+        }
+        writer.write_record(&row)?;
+        if !place.is_resolved() {
+            let value = record[column].to_string();
+            failures.push(Failure {
+                line: record.position().map_or(0, |p| p.line()),
+                suggestions: suggestions.get(&value).cloned().unwrap_or_default(),
+                value,
+            });
+        }
+    }
+    writer.flush()?;
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+    match &args.report {
+        Some(path) => write_report(&failures, path)?,
+        None => {
+            for f in &failures {
+                let hint = if f.suggestions.is_empty() {
+                    String::new()
+                } else {
+                    format!(", did you mean {}?", f.suggestions.join(", "))
+                };
+                eprintln!("line {}: {:?} not found{}", f.line, f.value, hint);
+            }
+        }
+    }
+    Err(NotFound(format!(
+        "{} of {} rows could not be resolved",
+        failures.len(),
+        records.len()
+    ))
+    .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_fields() {
+        let fields = |f: &[&str]| f.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(check_fields(&fields(&["iso3", "continent", "population"]), Kind::Country).is_ok());
+        assert!(check_fields(&fields(&["timezone"]), Kind::City).is_ok());
+        assert!(check_fields(&fields(&["timezone"]), Kind::Country).is_err());
+    }
+
+    #[test]
+    fn test_fields() {
+        let gc = Geonamescache::global();
+        let country = gc.get_countries().get("FR");
+        let place = Place {
+            city: None,
+            country,
+        };
+        assert_eq!(field(gc, place, "iso3"), "FRA");
+        assert_eq!(field(gc, place, "continent"), "Europe");
+
+        let city = gc.get_cities().get("2988507");
+        let place = Place { city, country };
+        assert_eq!(field(gc, place, "name"), "Paris");
+        assert_eq!(field(gc, place, "population"), "2138551");
+        assert_eq!(field(gc, place, "country"), "France");
+        assert_eq!(field(gc, Place::default(), "iso3"), "");
+    }
+
+    #[test]
+    fn test_resolve_cities() {
+        let gc = Geonamescache::global();
+        let records = vec![
+            StringRecord::from(vec!["Springfield", "MA"]),
+            StringRecord::from(vec!["Springfield", "IL"]),
+            StringRecord::from(vec!["Atlantis", ""]),
+        ];
+        let places = resolve_cities(gc, &records, 0, None, Some(1));
+        let ids: Vec<Option<u32>> = places.iter().map(|p| p.city.map(|c| c.geonameid)).collect();
+        assert_eq!(ids, [Some(4951788), Some(4250542), None]);
+    }
+}
@@ -0,0 +1,317 @@
+//! The `geonamescache` command-line tool, built with the `cli` feature.
+//!
+//! Every lookup command prints a table, or JSON with `--json`, and exits with status 1
+//! when nothing matches.
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use geonamescache::{City, Country, Geonamescache, NearestCity, UsCounty};
+use serde::Serialize;
+
+mod enrich;
+mod table;
+
+use enrich::EnrichArgs;
+use table::Table;
+
+#[derive(Parser)]
+#[command(version, about = "Look up countries, cities and US counties")]
+struct Cli {
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Parse a coordinate in degrees, rejecting NaN, infinities and values beyond `max`.
+fn coordinate(s: &str, max: f64) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|v| (-max..=max).contains(v))
+        .ok_or_else(|| format!("expected a number between -{} and {}", max, max))
+}
+
+fn latitude(s: &str) -> Result<f64, String> {
+    coordinate(s, 90.0)
+}
+
+fn longitude(s: &str) -> Result<f64, String> {
+    coordinate(s, 180.0)
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show a country given by name, ISO code, ISO3 code or alias
+    Country { query: String },
+    /// List the cities with this name
+    City { name: String },
+    /// List the cities with this name or alternate name, ignoring case
+    Search { query: String },
+    /// Show the city closest to the coordinates
+    #[command(allow_negative_numbers = true)]
+    Nearest {
+        #[arg(value_parser = latitude)]
+        lat: f64,
+        #[arg(value_parser = longitude)]
+        lon: f64,
+    },
+    /// Show the US county with this FIPS code
+    County { fips: String },
+    /// Map country values from one field to another, e.g. names to ISO3 codes
+    ///
+    /// The values are read from standard input, one per line, if none are given.
+    Map {
+        /// Field the values are in
+        #[arg(long, default_value = "name")]
+        from: String,
+        /// Field to map them to
+        #[arg(long, default_value = "iso")]
+        to: String,
+        /// Values to map
+        values: Vec<String>,
+    },
+    /// Append country or city columns to a CSV or TSV file
+    ///
+    /// Rows whose value doesn't resolve get empty columns and are reported, by line
+    /// number, on standard error or in the `--report` file.
+    Enrich(EnrichArgs),
+}
+
+/// A command found no result. Reported on stderr without the usage text.
+#[derive(Debug)]
+struct NotFound(String);
+
+impl std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for NotFound {}
+
+#[derive(Serialize)]
+struct Mapped<'a> {
+    value: &'a str,
+    result: Option<&'a str>,
+}
+
+/// Write to stdout, returning errors instead of panicking like `print!` so that a
+/// closed pipe (e.g. `| head`) ends the program quietly.
+fn print(value: impl Display) -> Result<(), Box<dyn Error>> {
+    write!(io::stdout().lock(), "{}", value)?;
+    Ok(())
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), Box<dyn Error>> {
+    print(serde_json::to_string_pretty(value)? + "\n")
+}
+
+fn country_table(country: &Country) -> Table {
+    let mut table = Table::default();
+    for field in Country::FIELDS {
+        table.push([
+            field.to_string(),
+            country.field(field).unwrap().into_owned(),
+        ]);
+    }
+    table
+}
+
+fn cities_table<'a>(cities: impl IntoIterator<Item = &'a City>) -> Table {
+    let mut table = Table::new([
+        "geonameid",
+        "name",
+        "countrycode",
+        "admin1code",
+        "population",
+        "latitude",
+        "longitude",
+        "timezone",
+    ]);
+    for c in cities {
+        table.push([
+            c.geonameid.to_string(),
+            c.name.clone(),
+            c.countrycode.clone(),
+            c.admin1code.clone(),
+            c.population.to_string(),
+            c.latitude.to_string(),
+            c.longitude.to_string(),
+            c.timezone.clone(),
+        ]);
+    }
+    table
+}
+
+fn nearest_table(nearest: &NearestCity) -> Table {
+    let c = nearest.city;
+    let mut table = Table::default();
+    table.push(["geonameid".to_string(), c.geonameid.to_string()]);
+    table.push(["name".to_string(), c.name.clone()]);
+    table.push(["countrycode".to_string(), c.countrycode.clone()]);
+    table.push(["admin1code".to_string(), c.admin1code.clone()]);
+    table.push(["population".to_string(), c.population.to_string()]);
+    table.push(["latitude".to_string(), c.latitude.to_string()]);
+    table.push(["longitude".to_string(), c.longitude.to_string()]);
+    table.push(["timezone".to_string(), c.timezone.clone()]);
+    table.push([
+        "distance_km".to_string(),
+        format!("{:.1}", nearest.distance_km),
+    ]);
+    table
+}
+
+fn county_table(county: &UsCounty) -> Table {
+    let mut table = Table::default();
+    table.push(["fips", &county.fips]);
+    table.push(["name", &county.name]);
+    table.push(["state", &county.state]);
+    table
+}
+
+/// Largest cities first.
+fn by_population(mut cities: Vec<&City>) -> Vec<&City> {
+    cities.sort_by(|a, b| {
+        b.population
+            .cmp(&a.population)
+            .then(a.geonameid.cmp(&b.geonameid))
+    });
+    cities
+}
+
+fn print_cities(cities: Vec<&City>, json: bool) -> Result<(), Box<dyn Error>> {
+    if cities.is_empty() {
+        return Err(NotFound("no matching cities".into()).into());
+    }
+    let cities = by_population(cities);
+    if json {
+        print_json(&cities)
+    } else {
+        print(cities_table(cities))
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let gc = Geonamescache::global();
+    let json = cli.json;
+    match cli.command {
+        Command::Country { query } => {
+            let country = gc
+                .resolve_country(&query)
+                .ok_or_else(|| NotFound(format!("no country matches {:?}", query)))?;
+            if json {
+                print_json(country)?;
+            } else {
+                print(country_table(country))?;
+            }
+        }
+        Command::City { name } => {
+            let cities = gc.get_cities_by_name(&name);
+            print_cities(cities.values().collect(), json)?;
+        }
+        Command::Search { query } => print_cities(gc.search_cities(&query), json)?,
+        Command::Nearest { lat, lon } => {
+            let nearest = gc
+                .nearest_city_with_distance(lat, lon)
+                .ok_or_else(|| NotFound("no cities loaded".into()))?;
+            if json {
+                print_json(&nearest)?;
+            } else {
+                print(nearest_table(&nearest))?;
+            }
+        }
+        Command::County { fips } => {
+            let county = gc
+                .get_us_counties()
+                .iter()
+                .find(|c| c.fips == fips)
+                .ok_or_else(|| NotFound(format!("no US county with FIPS code {:?}", fips)))?;
+            if json {
+                print_json(county)?;
+            } else {
+                print(county_table(county))?;
+            }
+        }
+        Command::Map { from, to, values } => {
+            let mapper = gc.country_field_mapper(&from, &to)?;
+            let values = if values.is_empty() {
+                io::stdin().lock().lines().collect::<io::Result<_>>()?
+            } else {
+                values
+            };
+            let mapped: Vec<Mapped> = values
+                .iter()
+                .map(|v| Mapped {
+                    value: v,
+                    result: mapper.get(v.trim()),
+                })
+                .collect();
+            if json {
+                print_json(&mapped)?;
+            } else {
+                let mut table = Table::new([from.as_str(), to.as_str()]);
+                for m in &mapped {
+                    table.push([m.value, m.result.unwrap_or("")]);
+                }
+                print(table)?;
+            }
+            if mapped.iter().any(|m| m.result.is_none()) {
+                return Err(NotFound("some values could not be mapped".into()).into());
+            }
+        }
+        Command::Enrich(args) => {
+            if json {
+                return Err("enrich writes CSV, --json isn't supported".into());
+            }
+            enrich::run(gc, args)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e)
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("geonamescache: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_negative_coordinates() {
+        let cli = Cli::try_parse_from(["geonamescache", "nearest", "-33.9", "-70.6"]).unwrap();
+        assert!(
+            matches!(cli.command, Command::Nearest { lat, lon } if lat == -33.9 && lon == -70.6)
+        );
+    }
+
+    #[test]
+    fn test_invalid_coordinates() {
+        for (lat, lon) in [("NaN", "0"), ("0", "inf"), ("200", "0"), ("0", "-180.5")] {
+            let cli = Cli::try_parse_from(["geonamescache", "nearest", lat, lon]);
+            assert!(cli.is_err(), "{} {}", lat, lon);
+        }
+    }
+}
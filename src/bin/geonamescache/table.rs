@@ -0,0 +1,87 @@
+//! Plain-text tables with left-aligned columns.
+use std::fmt;
+
+#[derive(Default)]
+pub struct Table {
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new<I, S>(header: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Table {
+            header: Some(header.into_iter().map(Into::into).collect()),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push<I, S>(&mut self, row: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &Vec<String>> {
+        self.header.iter().chain(&self.rows)
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut widths: Vec<usize> = Vec::new();
+        for line in self.lines() {
+            for (i, cell) in line.iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+        for line in self.lines() {
+            let mut out = String::new();
+            for (i, cell) in line.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("  ");
+                }
+                out.push_str(cell);
+                if i + 1 < line.len() {
+                    let padding = widths[i] - cell.chars().count();
+                    out.extend(std::iter::repeat_n(' ', padding));
+                }
+            }
+            writeln!(f, "{}", out.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alignment() {
+        let mut table = Table::new(["name", "population"]);
+        table.push(["Zürich", "341730"]);
+        table.push(["Samobor", "37633"]);
+        assert_eq!(
+            table.to_string(),
+            "name     population\nZürich   341730\nSamobor  37633\n"
+        );
+    }
+
+    #[test]
+    fn test_without_header() {
+        let mut table = Table::default();
+        table.push(["iso", "FR"]);
+        table.push(["name", ""]);
+        assert_eq!(table.to_string(), "iso   FR\nname\n");
+    }
+}
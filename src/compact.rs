@@ -0,0 +1,635 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::fmt;
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::geotypes::{AlternateName, City};
+use crate::snapshot::write::{Writer, HEADER_LEN, MAGIC, PREFERRED, SHORT, VERSION};
+use crate::snapshot::{record, write_snapshot, SnapshotError};
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Where each section of a snapshot starts, in bytes, and how many entries it has.
+#[derive(Clone, Copy, Debug, Default)]
+struct Layout {
+    cities: usize,
+    alternatenames: usize,
+    localized_names: usize,
+    strings: usize,
+    geonameids: usize,
+    names: usize,
+    latitudes: usize,
+    longitudes: usize,
+    countrycodes: usize,
+    populations: usize,
+    timezones: usize,
+    admin1codes: usize,
+    alternatename_offsets: usize,
+    alternatename_syms: usize,
+    localized_name_offsets: usize,
+    localized_name_entries: usize,
+    name_keys: usize,
+    by_name: usize,
+    string_starts: usize,
+    string_bytes: usize,
+}
+
+impl Layout {
+    /// Read the header and validate every section it describes, so that reading the
+    /// snapshot afterwards can't go out of bounds or split a character.
+    fn read(bytes: &[u8]) -> Result<Layout, SnapshotError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(SnapshotError::Truncated);
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let header = |i: usize| read_u32(bytes, 4 + 4 * i);
+        if header(0) != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(header(0)));
+        }
+        let [cities, alternatenames, localized_names, strings, string_bytes] =
+            [1, 2, 3, 4, 5].map(|i| header(i) as usize);
+
+        // The counts come from the file, so the sizes may not even fit in a `usize`.
+        let mut end = Some(HEADER_LEN);
+        let mut section = |entries: Option<usize>, width: usize| {
+            let start = end.unwrap_or(0);
+            end = entries
+                .and_then(|n| n.checked_mul(width))
+                .and_then(|len| end?.checked_add(len));
+            start
+        };
+        let rows = Some(cities);
+        let layout = Layout {
+            cities,
+            alternatenames,
+            localized_names,
+            strings,
+            geonameids: section(rows, 4),
+            names: section(rows, 4),
+            latitudes: section(rows, 8),
+            longitudes: section(rows, 8),
+            countrycodes: section(rows, 4),
+            populations: section(rows, 4),
+            timezones: section(rows, 4),
+            admin1codes: section(rows, 4),
+            alternatename_offsets: section(cities.checked_add(1), 4),
+            alternatename_syms: section(Some(alternatenames), 4),
+            localized_name_offsets: section(cities.checked_add(1), 4),
+            localized_name_entries: section(Some(localized_names), 12),
+            name_keys: section(rows, 4),
+            by_name: section(rows, 4),
+            string_starts: section(strings.checked_add(1), 4),
+            string_bytes: section(Some(string_bytes), 1),
+        };
+        match end {
+            Some(end) if end == bytes.len() => {}
+            Some(end) if end < bytes.len() => {
+                return Err(SnapshotError::Corrupt("trailing bytes"));
+            }
+            _ => return Err(SnapshotError::Truncated),
+        }
+        layout.validate(bytes)?;
+        Ok(layout)
+    }
+
+    fn u32s<'b>(
+        &self,
+        bytes: &'b [u8],
+        section: usize,
+        entries: usize,
+    ) -> impl Iterator<Item = u32> + 'b {
+        (0..entries).map(move |i| read_u32(bytes, section + 4 * i))
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        let strings = std::str::from_utf8(&bytes[self.string_bytes..])
+            .map_err(|_| SnapshotError::InvalidUtf8)?;
+        let starts = self.u32s(bytes, self.string_starts, self.strings + 1);
+        check_offsets(starts, strings.len(), "string starts")?;
+        if !self
+            .u32s(bytes, self.string_starts, self.strings + 1)
+            .all(|start| strings.is_char_boundary(start as usize))
+        {
+            return Err(SnapshotError::InvalidUtf8);
+        }
+
+        let symbols = |section, entries, name| {
+            if self
+                .u32s(bytes, section, entries)
+                .all(|sym| (sym as usize) < self.strings)
+            {
+                Ok(())
+            } else {
+                Err(SnapshotError::Corrupt(name))
+            }
+        };
+        symbols(self.names, self.cities, "name")?;
+        symbols(self.countrycodes, self.cities, "countrycode")?;
+        symbols(self.timezones, self.cities, "timezone")?;
+        symbols(self.admin1codes, self.cities, "admin1code")?;
+        symbols(
+            self.alternatename_syms,
+            self.alternatenames,
+            "alternate name",
+        )?;
+        symbols(self.name_keys, self.cities, "name key")?;
+        let localized = self.u32s(bytes, self.localized_name_entries, 3 * self.localized_names);
+        for (i, value) in localized.enumerate() {
+            let valid = match i % 3 {
+                2 => value & !(PREFERRED | SHORT) == 0,
+                _ => (value as usize) < self.strings,
+            };
+            if !valid {
+                return Err(SnapshotError::Corrupt("localized name"));
+            }
+        }
+
+        let offsets = self.u32s(bytes, self.alternatename_offsets, self.cities + 1);
+        check_offsets(offsets, self.alternatenames, "alternate name offsets")?;
+        let offsets = self.u32s(bytes, self.localized_name_offsets, self.cities + 1);
+        check_offsets(offsets, self.localized_names, "localized name offsets")?;
+        if !self
+            .u32s(bytes, self.by_name, self.cities)
+            .all(|row| (row as usize) < self.cities)
+        {
+            return Err(SnapshotError::Corrupt("rows sorted by name"));
+        }
+
+        // Lookups are binary searches, which need both columns sorted.
+        let ids: Vec<u32> = self.u32s(bytes, self.geonameids, self.cities).collect();
+        if ids.windows(2).any(|w| w[0] > w[1]) {
+            return Err(SnapshotError::Corrupt("geonameids out of order"));
+        }
+        let string = |sym: u32| {
+            let start = read_u32(bytes, self.string_starts + 4 * sym as usize) as usize;
+            let end = read_u32(bytes, self.string_starts + 4 * (sym as usize + 1)) as usize;
+            &strings[start..end]
+        };
+        let keys: Vec<&str> = self
+            .u32s(bytes, self.name_keys, self.cities)
+            .map(string)
+            .collect();
+        if keys.windows(2).any(|w| w[0] > w[1]) {
+            return Err(SnapshotError::Corrupt("name keys out of order"));
+        }
+        Ok(())
+    }
+}
+
+/// Check that `offsets` start at 0, never decrease and end at `len`.
+fn check_offsets(
+    mut offsets: impl Iterator<Item = u32>,
+    len: usize,
+    name: &'static str,
+) -> Result<(), SnapshotError> {
+    let mut previous = 0;
+    if offsets.next() != Some(0) {
+        return Err(SnapshotError::Corrupt(name));
+    }
+    for offset in offsets {
+        if offset < previous {
+            return Err(SnapshotError::Corrupt(name));
+        }
+        previous = offset;
+    }
+    if previous as usize != len {
+        return Err(SnapshotError::Corrupt(name));
+    }
+    Ok(())
+}
+
+/// The city dataset in a compact, column-oriented layout: the
+/// [snapshot format](crate::snapshot), read in place.
+///
+/// Strings are interned in a single buffer and referred to by `u32` indices, and rows
+/// are sorted by geonameid. Coordinates are kept as `f64`, so nothing is lost compared
+/// to [`City`]. The store either owns its bytes, when built from records with
+/// [`CompactCities::from_cities`], or borrows them, e.g. from a memory-mapped file
+/// opened with [`CompactCities::from_bytes`] or the snapshot embedded with the
+/// `snapshot` feature. Records are read through [`CityView`], which borrows from the
+/// store instead of cloning, and can be turned into a [`City`] when needed.
+///
+/// # Examples
+/// ```
+/// use geonamescache::CompactCities;
+/// let cities = CompactCities::embedded();
+/// let samobor = cities.get(3191316).unwrap();
+/// assert_eq!(samobor.name(), "Samobor");
+/// assert!(cities.by_name("madrid").count() >= 2);
+/// ```
+#[derive(Clone, Default)]
+pub struct CompactCities<'a> {
+    bytes: Cow<'a, [u8]>,
+    layout: Layout,
+}
+
+impl<'a> CompactCities<'a> {
+    /// The bundled city dataset. With the `snapshot` feature it is read in place from
+    /// the snapshot generated at build time; otherwise `cities.json` is loaded
+    /// straight into the compact layout, without building the [`City`] records first.
+    #[cfg(feature = "embedded-cities")]
+    pub fn embedded() -> CompactCities<'static> {
+        #[cfg(feature = "snapshot")]
+        {
+            static BYTES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/cities.snapshot"));
+            CompactCities::from_bytes(BYTES).expect("Internal error when loading cities.snapshot")
+        }
+        #[cfg(not(feature = "snapshot"))]
+        serde_json::from_str(include_str!("cities.json"))
+            .expect("Internal error when loading file: cities.json")
+    }
+
+    pub fn from_cities<'c, I>(cities: I) -> CompactCities<'static>
+    where
+        I: IntoIterator<Item = &'c City>,
+    {
+        CompactCities::from_vec(write_snapshot(cities)).expect("Internal error when writing cities")
+    }
+
+    /// Open a snapshot written by [`crate::snapshot::write_snapshot`] or
+    /// [`CompactCities::as_bytes`], without copying it.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, SnapshotError> {
+        let layout = Layout::read(bytes)?;
+        Ok(CompactCities {
+            bytes: Cow::Borrowed(bytes),
+            layout,
+        })
+    }
+
+    /// Like [`CompactCities::from_bytes`], taking ownership of the bytes.
+    pub fn from_vec(bytes: Vec<u8>) -> Result<CompactCities<'static>, SnapshotError> {
+        let layout = Layout::read(&bytes)?;
+        Ok(CompactCities {
+            bytes: Cow::Owned(bytes),
+            layout,
+        })
+    }
+
+    /// The store in the snapshot format, e.g. to be written to a file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.layout.cities
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, geonameid: u32) -> Option<CityView<'_>> {
+        let row = partition_point(self.len(), |row| self.geonameid(row) < geonameid);
+        (row < self.len() && self.geonameid(row) == geonameid)
+            .then_some(CityView { store: self, row })
+    }
+
+    /// All cities, ordered by geonameid.
+    pub fn iter(&self) -> impl Iterator<Item = CityView<'_>> {
+        (0..self.len()).map(move |row| CityView { store: self, row })
+    }
+
+    /// Cities with the given name (case-insensitive).
+    pub fn by_name(&self, name: &str) -> impl Iterator<Item = CityView<'_>> {
+        let name = name.to_lowercase();
+        let key = move |i: usize| self.string(self.u32(self.layout.name_keys, i));
+        let start = partition_point(self.len(), |i| key(i) < name.as_str());
+        (start..self.len())
+            .take_while(move |&i| key(i) == name)
+            .map(move |i| CityView {
+                store: self,
+                row: self.u32(self.layout.by_name, i) as usize,
+            })
+    }
+
+    /// Bytes allocated on the heap by the store, none if it borrows its bytes.
+    pub fn heap_size(&self) -> usize {
+        match &self.bytes {
+            Cow::Borrowed(_) => 0,
+            Cow::Owned(bytes) => bytes.capacity(),
+        }
+    }
+
+    /// Entry `i` of the `u32` column starting at `section`.
+    fn u32(&self, section: usize, i: usize) -> u32 {
+        read_u32(&self.bytes, section + 4 * i)
+    }
+
+    fn f64(&self, section: usize, i: usize) -> f64 {
+        let at = section + 8 * i;
+        f64::from_le_bytes(self.bytes[at..at + 8].try_into().unwrap())
+    }
+
+    fn geonameid(&self, row: usize) -> u32 {
+        self.u32(self.layout.geonameids, row)
+    }
+
+    fn string(&self, sym: u32) -> &str {
+        let sym = sym as usize;
+        let start = self.u32(self.layout.string_starts, sym) as usize;
+        let end = self.u32(self.layout.string_starts, sym + 1) as usize;
+        let bytes = &self.bytes[self.layout.string_bytes + start..self.layout.string_bytes + end];
+        std::str::from_utf8(bytes).expect("Internal error when reading a city string")
+    }
+}
+
+/// Index of the first element in `0..len` for which `pred` is false.
+fn partition_point<P: Fn(usize) -> bool>(len: usize, pred: P) -> usize {
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+impl fmt::Debug for CompactCities<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompactCities")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactCities<'static> {
+    /// Deserialise the `{geonameid: city}` map of `cities.json` one city at a time.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CitiesVisitor;
+
+        impl<'de> Visitor<'de> for CitiesVisitor {
+            type Value = CompactCities<'static>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of geonameids to cities")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut writer = Writer::default();
+                while let Some((_, city)) = map.next_entry::<serde::de::IgnoredAny, City>()? {
+                    writer.push(record(&city));
+                }
+                CompactCities::from_vec(writer.finish()).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_map(CitiesVisitor)
+    }
+}
+
+/// A city in a [`CompactCities`] store. Cheap to copy; the strings it returns borrow
+/// from the store.
+#[derive(Clone, Copy)]
+pub struct CityView<'s> {
+    store: &'s CompactCities<'s>,
+    row: usize,
+}
+
+impl<'s> CityView<'s> {
+    fn string(&self, section: usize) -> &'s str {
+        let store: &'s CompactCities = self.store;
+        store.string(store.u32(section, self.row))
+    }
+
+    pub fn geonameid(&self) -> u32 {
+        self.store.geonameid(self.row)
+    }
+
+    pub fn name(&self) -> &'s str {
+        self.string(self.store.layout.names)
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.store.f64(self.store.layout.latitudes, self.row)
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.store.f64(self.store.layout.longitudes, self.row)
+    }
+
+    pub fn countrycode(&self) -> &'s str {
+        self.string(self.store.layout.countrycodes)
+    }
+
+    pub fn population(&self) -> u32 {
+        self.store.u32(self.store.layout.populations, self.row)
+    }
+
+    pub fn timezone(&self) -> &'s str {
+        self.string(self.store.layout.timezones)
+    }
+
+    pub fn admin1code(&self) -> &'s str {
+        self.string(self.store.layout.admin1codes)
+    }
+
+    pub fn alternatenames(&self) -> impl Iterator<Item = &'s str> {
+        let store: &'s CompactCities = self.store;
+        let start = store.u32(store.layout.alternatename_offsets, self.row) as usize;
+        let end = store.u32(store.layout.alternatename_offsets, self.row + 1) as usize;
+        (start..end).map(move |i| store.string(store.u32(store.layout.alternatename_syms, i)))
+    }
+
+    /// The names tagged by language, as in [`City::localized_names`].
+    pub fn localized_names(&self) -> impl Iterator<Item = AlternateName> + 's {
+        let store: &'s CompactCities = self.store;
+        let start = store.u32(store.layout.localized_name_offsets, self.row) as usize;
+        let end = store.u32(store.layout.localized_name_offsets, self.row + 1) as usize;
+        (start..end).map(move |i| {
+            let field = |j| store.u32(store.layout.localized_name_entries, 3 * i + j);
+            AlternateName {
+                name: store.string(field(0)).to_string(),
+                lang: store.string(field(1)).to_string(),
+                is_preferred_name: field(2) & PREFERRED != 0,
+                is_short_name: field(2) & SHORT != 0,
+            }
+        })
+    }
+
+    /// Copy the city into an owned record.
+    pub fn to_city(&self) -> City {
+        City {
+            geonameid: self.geonameid(),
+            name: self.name().to_string(),
+            latitude: self.latitude(),
+            longitude: self.longitude(),
+            countrycode: self.countrycode().to_string(),
+            population: self.population(),
+            timezone: self.timezone().to_string(),
+            admin1code: self.admin1code().to_string(),
+            alternatenames: self.alternatenames().map(str::to_string).collect(),
+            localized_names: self.localized_names().collect(),
+        }
+    }
+}
+
+impl fmt::Debug for CityView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CityView")
+            .field("geonameid", &self.geonameid())
+            .field("name", &self.name())
+            .field("countrycode", &self.countrycode())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn city(geonameid: u32, name: &str, alternatenames: &[&str]) -> City {
+        City {
+            geonameid,
+            name: name.into(),
+            latitude: 40.4165,
+            longitude: -3.70256,
+            countrycode: "ES".into(),
+            population: 1000,
+            timezone: "Europe/Madrid".into(),
+            admin1code: "29".into(),
+            alternatenames: alternatenames.iter().map(|&n| n.into()).collect(),
+            localized_names: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut madrid = city(3, "Madrid", &["MAD", "Madri"]);
+        madrid.localized_names = vec![AlternateName {
+            name: "Madrit".into(),
+            lang: "de".into(),
+            is_preferred_name: true,
+            is_short_name: false,
+        }];
+        let cities = [
+            madrid,
+            city(1, "Getafe", &[]),
+            city(2, "madrid", &["Madri"]),
+        ];
+        let store = CompactCities::from_cities(&cities);
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(
+            store.iter().map(|c| c.geonameid()).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        for c in &cities {
+            assert_eq!(&store.get(c.geonameid).unwrap().to_city(), c);
+        }
+        assert!(store.get(4).is_none());
+    }
+
+    #[test]
+    fn test_interning() {
+        let cities = [city(1, "Madrid", &["Madri"]), city(2, "Madri", &["Madrid"])];
+        let store = CompactCities::from_cities(&cities);
+
+        // "Madrid", "Madri", "ES", "Europe/Madrid", "29" and the name keys "madrid" and
+        // "madri", each stored once.
+        assert_eq!(read_u32(store.as_bytes(), 20), 7);
+    }
+
+    #[test]
+    fn test_by_name() {
+        let cities = [
+            city(3, "Madrid", &[]),
+            city(1, "Getafe", &[]),
+            city(2, "MADRID", &[]),
+            city(4, "Zaragoza", &[]),
+        ];
+        let store = CompactCities::from_cities(&cities);
+
+        let mut ids: Vec<_> = store.by_name("madrid").map(|c| c.geonameid()).collect();
+        ids.sort();
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(store.by_name("Zaragoza").count(), 1);
+        assert_eq!(store.by_name("Atlantis").count(), 0);
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let cities = [city(1, "Getafe", &["Xetafe"])];
+        let owned = CompactCities::from_cities(&cities);
+        let borrowed = CompactCities::from_bytes(owned.as_bytes()).unwrap();
+
+        assert!(owned.heap_size() > 0);
+        assert_eq!(borrowed.heap_size(), 0);
+        assert_eq!(borrowed.get(1).unwrap().to_city(), cities[0]);
+    }
+
+    #[test]
+    fn test_validation() {
+        let cities = [city(1, "Zürich", &["Zurich"]), city(2, "Getafe", &[])];
+        let bytes = CompactCities::from_cities(&cities).as_bytes().to_vec();
+        let layout = Layout::read(&bytes).unwrap();
+        let with = |at: usize, value: u32| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            CompactCities::from_vec(bytes).unwrap_err()
+        };
+
+        let past_end = layout.strings as u32;
+        assert_eq!(with(layout.names, past_end), SnapshotError::Corrupt("name"));
+        assert_eq!(
+            with(layout.alternatename_syms, past_end),
+            SnapshotError::Corrupt("alternate name")
+        );
+        assert_eq!(
+            with(layout.alternatename_offsets + 4, 5),
+            SnapshotError::Corrupt("alternate name offsets")
+        );
+        assert_eq!(
+            with(layout.by_name, 2),
+            SnapshotError::Corrupt("rows sorted by name")
+        );
+        assert_eq!(
+            with(layout.geonameids, 3),
+            SnapshotError::Corrupt("geonameids out of order")
+        );
+        // "Zürich" is the first string: end it in the middle of the "ü".
+        assert_eq!(
+            with(layout.string_starts + 4, 2),
+            SnapshotError::InvalidUtf8
+        );
+        assert_eq!(
+            with(layout.string_starts + 4, u32::MAX),
+            SnapshotError::Corrupt("string starts")
+        );
+    }
+
+    #[test]
+    fn test_deserialise() {
+        let json = r#"{
+            "2": {"geonameid": 2, "name": "Getafe", "latitude": 40.3, "longitude": -3.7,
+                  "countrycode": "ES", "population": 180000, "timezone": "Europe/Madrid",
+                  "admin1code": "29", "alternatenames": ["Xetafe"]},
+            "1": {"geonameid": 1, "name": "Samobor", "latitude": 45.8, "longitude": 15.7,
+                  "countrycode": "HR", "population": 37633, "timezone": "Europe/Zagreb",
+                  "admin1code": "01", "alternatenames": []}
+        }"#;
+        let store: CompactCities = serde_json::from_str(json).unwrap();
+
+        assert_eq!(store.len(), 2);
+        let getafe = store.get(2).unwrap();
+        assert_eq!(getafe.name(), "Getafe");
+        assert_eq!(getafe.population(), 180000);
+        assert_eq!(getafe.latitude(), 40.3);
+        assert_eq!(getafe.alternatenames().collect::<Vec<_>>(), ["Xetafe"]);
+        assert_eq!(store.get(1).unwrap().timezone(), "Europe/Zagreb");
+    }
+}
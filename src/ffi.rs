@@ -0,0 +1,249 @@
+//! C ABI, enabled with the `ffi` feature and built as a library by `bindings/ffi`.
+//! The declarations are in `include/geonamescache.h`.
+//!
+//! Ownership: every pointer returned by a `gnc_*` function is owned by the caller and
+//! must be released with the matching `gnc_*_free` function. Pointers passed in are
+//! only borrowed for the duration of the call. Functions return `NULL` when there is
+//! no result, when an argument is `NULL`, when a string isn't valid UTF-8 or when
+//! coordinates are out of range. The string fields of a returned city are never
+//! `NULL`.
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::mappers::CountryMapper;
+use crate::{City, Geonamescache};
+
+/// A city with NUL-terminated strings, returned by [`gnc_city_by_id`] and
+/// [`gnc_nearest_city`].
+#[repr(C)]
+pub struct GncCity {
+    pub geonameid: u32,
+    pub name: *mut c_char,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub countrycode: *mut c_char,
+    pub population: u32,
+    pub timezone: *mut c_char,
+    pub admin1code: *mut c_char,
+}
+
+pub struct GncCountryMapper {
+    mapper: CountryMapper<'static>,
+}
+
+/// Borrow a C string as `&str`, or `None` if it is `NULL` or not UTF-8.
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Copy a string for the caller, who frees it with [`gnc_string_free`]. Interior NULs
+/// can't be represented in a C string and are dropped.
+fn to_c_string(s: &str) -> *mut c_char {
+    let bytes: Vec<u8> = s.bytes().filter(|&b| b != 0).collect();
+    CString::new(bytes).unwrap_or_default().into_raw()
+}
+
+fn to_c_city(city: &City) -> *mut GncCity {
+    Box::into_raw(Box::new(GncCity {
+        geonameid: city.geonameid,
+        name: to_c_string(&city.name),
+        latitude: city.latitude,
+        longitude: city.longitude,
+        countrycode: to_c_string(&city.countrycode),
+        population: city.population,
+        timezone: to_c_string(&city.timezone),
+        admin1code: to_c_string(&city.admin1code),
+    }))
+}
+
+/// Create a cache. Datasets are loaded on first use.
+#[no_mangle]
+pub extern "C" fn gnc_new() -> *mut Geonamescache {
+    Box::into_raw(Box::new(Geonamescache::new()))
+}
+
+/// # Safety
+/// `gc` must be `NULL` or a pointer returned by [`gnc_new`] that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_free(gc: *mut Geonamescache) {
+    if !gc.is_null() {
+        drop(Box::from_raw(gc));
+    }
+}
+
+/// ISO code of the country a free-text value refers to, see
+/// [`Geonamescache::resolve_country`].
+///
+/// # Safety
+/// `gc` must be a live cache and `query` `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_resolve_country(
+    gc: *const Geonamescache,
+    query: *const c_char,
+) -> *mut c_char {
+    match (gc.as_ref(), to_str(query)) {
+        (Some(gc), Some(query)) => gc
+            .resolve_country(query)
+            .map_or(ptr::null_mut(), |c| to_c_string(&c.iso)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Create a mapper between two country fields given by name, e.g. `"name"` and
+/// `"iso3"`. Returns `NULL` for unknown fields. The mapper doesn't borrow the cache.
+///
+/// # Safety
+/// `gc` must be a live cache and `from` and `to` `NULL` or NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_country_mapper_new(
+    gc: *const Geonamescache,
+    from: *const c_char,
+    to: *const c_char,
+) -> *mut GncCountryMapper {
+    let (gc, from, to) = match (gc.as_ref(), to_str(from), to_str(to)) {
+        (Some(gc), Some(from), Some(to)) => (gc, from, to),
+        _ => return ptr::null_mut(),
+    };
+    gc.country_field_mapper(from, to)
+        .map_or(ptr::null_mut(), |mapper| {
+            Box::into_raw(Box::new(GncCountryMapper { mapper }))
+        })
+}
+
+/// The value mapped to `key` (case-insensitive).
+///
+/// # Safety
+/// `mapper` must be a live mapper and `key` `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_country_mapper_get(
+    mapper: *const GncCountryMapper,
+    key: *const c_char,
+) -> *mut c_char {
+    match (mapper.as_ref(), to_str(key)) {
+        (Some(m), Some(key)) => m.mapper.get(key).map_or(ptr::null_mut(), to_c_string),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `mapper` must be `NULL` or a pointer returned by [`gnc_country_mapper_new`] that
+/// hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_country_mapper_free(mapper: *mut GncCountryMapper) {
+    if !mapper.is_null() {
+        drop(Box::from_raw(mapper));
+    }
+}
+
+/// # Safety
+/// `gc` must be a live cache.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_city_by_id(gc: *const Geonamescache, geonameid: u32) -> *mut GncCity {
+    gc.as_ref()
+        .and_then(|gc| gc.get_cities().get(&geonameid.to_string()))
+        .map_or(ptr::null_mut(), to_c_city)
+}
+
+/// The city closest to the given coordinates, or `NULL` if they aren't finite or are
+/// outside ±90 latitude and ±180 longitude.
+///
+/// # Safety
+/// `gc` must be a live cache.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_nearest_city(
+    gc: *const Geonamescache,
+    lat: f64,
+    lon: f64,
+) -> *mut GncCity {
+    gc.as_ref()
+        .and_then(|gc| gc.nearest_city(lat, lon))
+        .map_or(ptr::null_mut(), to_c_city)
+}
+
+/// # Safety
+/// `city` must be `NULL` or a pointer returned by this library that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_city_free(city: *mut GncCity) {
+    if city.is_null() {
+        return;
+    }
+    let city = Box::from_raw(city);
+    for s in [city.name, city.countrycode, city.timezone, city.admin1code] {
+        gnc_string_free(s);
+    }
+}
+
+/// # Safety
+/// `s` must be `NULL` or a string returned by this library that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gnc_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    unsafe fn take_string(s: *mut c_char) -> Option<String> {
+        let value = to_str(s).map(str::to_string);
+        gnc_string_free(s);
+        value
+    }
+
+    #[test]
+    fn test_country_lookups() {
+        unsafe {
+            let gc = gnc_new();
+            let iso = gnc_resolve_country(gc, c"Atlantis".as_ptr());
+            assert_eq!(take_string(iso), None);
+            let iso = gnc_resolve_country(gc, c"DEU".as_ptr());
+            assert_eq!(take_string(iso).as_deref(), Some("DE"));
+
+            let mapper = gnc_country_mapper_new(gc, c"name".as_ptr(), c"iso3".as_ptr());
+            assert!(!mapper.is_null());
+            let iso3 = gnc_country_mapper_get(mapper, c"United Kingdom".as_ptr());
+            assert_eq!(take_string(iso3).as_deref(), Some("GBR"));
+            assert!(gnc_country_mapper_get(mapper, ptr::null()).is_null());
+            gnc_country_mapper_free(mapper);
+
+            assert!(gnc_country_mapper_new(gc, c"name".as_ptr(), c"nope".as_ptr()).is_null());
+            gnc_free(gc);
+        }
+    }
+
+    #[test]
+    fn test_city_lookups() {
+        unsafe {
+            let gc = gnc_new();
+            let city = gnc_city_by_id(gc, 3191316);
+            assert_eq!((*city).population, 37633);
+            assert_eq!(to_str((*city).name), Some("Samobor"));
+            assert_eq!(to_str((*city).countrycode), Some("HR"));
+            gnc_city_free(city);
+
+            assert!(gnc_city_by_id(gc, 0).is_null());
+
+            let city = gnc_nearest_city(gc, 40.42, -3.7);
+            assert_eq!((*city).geonameid, 3117735);
+            gnc_city_free(city);
+            assert!(gnc_nearest_city(gc, f64::NAN, -3.7).is_null());
+            assert!(gnc_nearest_city(gc, 40.42, 200.0).is_null());
+            gnc_free(gc);
+        }
+    }
+
+    #[test]
+    fn test_interior_nul() {
+        unsafe {
+            assert_eq!(
+                take_string(to_c_string("Sa\0mobor")).as_deref(),
+                Some("Samobor")
+            );
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance in kilometres between two (latitude, longitude) points.
+#[cfg(feature = "std")]
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (lon2 - lon1).to_radians();
+
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Whether the latitude and longitude are finite and within ±90 and ±180 degrees.
+pub fn is_valid_coordinate(lat: f64, lon: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+/// A point on the globe, in degrees.
+///
+/// Points and [`BoundingBox`]es compare their coordinates bit for bit, so that they are
+/// `Eq` and so are the records holding them: `NaN` equals itself and `0.0` differs
+/// from `-0.0`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Point {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.lat.to_bits() == other.lat.to_bits() && self.lon.to_bits() == other.lon.to_bits()
+    }
+}
+
+impl Eq for Point {}
+
+/// A latitude/longitude rectangle, in degrees. `west` is greater than `east` when the
+/// box crosses the antimeridian.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct BoundingBox {
+    pub north: f64,
+    pub south: f64,
+    pub east: f64,
+    pub west: f64,
+}
+
+impl PartialEq for BoundingBox {
+    fn eq(&self, other: &Self) -> bool {
+        let bits = |b: &Self| [b.north, b.south, b.east, b.west].map(f64::to_bits);
+        bits(self) == bits(other)
+    }
+}
+
+impl Eq for BoundingBox {}
+
+impl BoundingBox {
+    pub fn new(north: f64, south: f64, east: f64, west: f64) -> Self {
+        Self {
+            north,
+            south,
+            east,
+            west,
+        }
+    }
+
+    /// Smallest box containing all the points, or `None` if there are none. The box
+    /// crosses the antimeridian when that makes it narrower, e.g. for Fiji or Russia.
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Point>,
+    {
+        let (mut north, mut south) = (f64::NEG_INFINITY, f64::INFINITY);
+        let mut lons = Vec::new();
+        for p in points {
+            north = north.max(p.lat);
+            south = south.min(p.lat);
+            lons.push(p.lon);
+        }
+        lons.sort_by(f64::total_cmp);
+        let (&first, &last) = (lons.first()?, lons.last()?);
+
+        // The box spans every longitude except the widest gap between two points. If
+        // that gap isn't the one across the antimeridian, the box crosses it.
+        let (mut gap, mut west, mut east) = (first + 360.0 - last, first, last);
+        for w in lons.windows(2) {
+            if w[1] - w[0] > gap {
+                (gap, west, east) = (w[1] - w[0], w[1], w[0]);
+            }
+        }
+        Some(Self::new(north, south, east, west))
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.south..=self.north).contains(&lat)
+            && self.lon_ranges().any(|(w, e)| (w..=e).contains(&lon))
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.south <= other.north
+            && other.south <= self.north
+            && self
+                .lon_ranges()
+                .any(|(w1, e1)| other.lon_ranges().any(|(w2, e2)| w1 <= e2 && w2 <= e1))
+    }
+
+    /// Longitude intervals covered by the box, split in two at the antimeridian.
+    fn lon_ranges(&self) -> impl Iterator<Item = (f64, f64)> {
+        let (first, second) = if self.west <= self.east {
+            ((self.west, self.east), None)
+        } else {
+            ((self.west, 180.0), Some((-180.0, self.east)))
+        };
+        core::iter::once(first).chain(second)
+    }
+}
+
+/// Mean position of the points, or `None` if there are none. Longitudes are averaged
+/// within the points' [bounding box](BoundingBox::from_points), so points on both sides
+/// of the antimeridian average to a longitude near it rather than near 0.
+pub fn centroid(points: &[Point]) -> Option<Point> {
+    let bbox = BoundingBox::from_points(points.iter().copied())?;
+    let n = points.len() as f64;
+    let unwrap = |lon: f64| if lon < bbox.west { lon + 360.0 } else { lon };
+    let lon = points.iter().map(|p| unwrap(p.lon)).sum::<f64>() / n;
+    Some(Point {
+        lat: points.iter().map(|p| p.lat).sum::<f64>() / n,
+        lon: if lon > 180.0 { lon - 360.0 } else { lon },
+    })
+}
+
+/// A ring of `[longitude, latitude]` positions, in GeoJSON order.
+type Ring = Vec<[f64; 2]>;
+
+/// GeoJSON `MultiPolygon` coordinates with a precomputed bounding box, so that
+/// point queries can discard most shapes without walking their rings.
+#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+#[serde(from = "Vec<Vec<Ring>>")]
+pub struct MultiPolygon {
+    polygons: Vec<Vec<Ring>>,
+    /// `[min_lon, min_lat, max_lon, max_lat]`
+    bbox: [f64; 4],
+}
+
+impl From<Vec<Vec<Ring>>> for MultiPolygon {
+    fn from(polygons: Vec<Vec<Ring>>) -> Self {
+        let mut bbox = [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for [lon, lat] in polygons.iter().filter_map(|p| p.first()).flatten() {
+            bbox[0] = bbox[0].min(*lon);
+            bbox[1] = bbox[1].min(*lat);
+            bbox[2] = bbox[2].max(*lon);
+            bbox[3] = bbox[3].max(*lat);
+        }
+        Self { polygons, bbox }
+    }
+}
+
+impl MultiPolygon {
+    fn bbox_contains(&self, lat: f64, lon: f64) -> bool {
+        let [min_lon, min_lat, max_lon, max_lat] = self.bbox;
+        (min_lon..=max_lon).contains(&lon) && (min_lat..=max_lat).contains(&lat)
+    }
+
+    /// Whether the point lies inside any of the polygons. The first ring of each
+    /// polygon is its exterior; the remaining rings are holes.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        self.bbox_contains(lat, lon)
+            && self.polygons.iter().any(|rings| match rings.split_first() {
+                Some((exterior, holes)) => {
+                    ring_contains(exterior, lat, lon)
+                        && !holes.iter().any(|h| ring_contains(h, lat, lon))
+                }
+                None => false,
+            })
+    }
+}
+
+/// Even-odd ray casting test.
+fn ring_contains(ring: &[[f64; 2]], lat: f64, lon: f64) -> bool {
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for (i, &[xi, yi]) in ring.iter().enumerate() {
+        let [xj, yj] = ring[j];
+        if (yi > lat) != (yj > lat) && lon < (xj - xi) * (lat - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn square(min: f64, max: f64) -> Ring {
+        vec![[min, min], [max, min], [max, max], [min, max], [min, min]]
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_haversine() {
+        assert_eq!(haversine_km(10.0, 20.0, 10.0, 20.0), 0.0);
+
+        // Madrid to Paris is roughly 1050 km.
+        let d = haversine_km(40.4165, -3.70256, 48.85341, 2.3488);
+        assert!((d - 1053.0).abs() < 5.0, "{}", d);
+    }
+
+    #[test]
+    fn test_eq() {
+        let p = Point {
+            lat: f64::NAN,
+            lon: 1.0,
+        };
+        assert_eq!(p, p);
+        assert_ne!(
+            Point { lat: 0.0, lon: 0.0 },
+            Point {
+                lat: -0.0,
+                lon: 0.0
+            }
+        );
+        let bbox = BoundingBox::new(44.0, 36.0, 4.0, -10.0);
+        assert_eq!(bbox, bbox);
+        assert_ne!(bbox, BoundingBox::new(44.0, 36.0, 4.0, -9.0));
+    }
+
+    #[test]
+    fn test_is_valid_coordinate() {
+        assert!(is_valid_coordinate(90.0, -180.0));
+        assert!(is_valid_coordinate(40.42, -3.7));
+        assert!(!is_valid_coordinate(90.5, 0.0));
+        assert!(!is_valid_coordinate(0.0, 200.0));
+        assert!(!is_valid_coordinate(f64::NAN, 0.0));
+        assert!(!is_valid_coordinate(0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_bbox_contains() {
+        let iberia = BoundingBox::new(44.0, 36.0, 4.0, -10.0);
+        assert!(iberia.contains(40.4, -3.7));
+        assert!(!iberia.contains(48.8, 2.3));
+
+        let pacific = BoundingBox::new(10.0, -20.0, -170.0, 170.0);
+        assert!(pacific.contains(0.0, 179.0));
+        assert!(pacific.contains(0.0, -175.0));
+        assert!(!pacific.contains(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_bbox_intersects() {
+        let iberia = BoundingBox::new(44.0, 36.0, 4.0, -10.0);
+        let france = BoundingBox::new(51.0, 42.0, 8.0, -5.0);
+        let japan = BoundingBox::new(45.0, 24.0, 146.0, 123.0);
+        let pacific = BoundingBox::new(10.0, -20.0, -170.0, 170.0);
+        let fiji = BoundingBox::new(-12.0, -21.0, -178.0, 177.0);
+
+        assert!(iberia.intersects(&france));
+        assert!(france.intersects(&iberia));
+        assert!(!iberia.intersects(&japan));
+        assert!(pacific.intersects(&fiji));
+        assert!(!pacific.intersects(&japan));
+    }
+
+    #[test]
+    fn test_bbox_from_points() {
+        assert_eq!(None, BoundingBox::from_points([]));
+
+        let points = [
+            Point { lat: 1.0, lon: 5.0 },
+            Point {
+                lat: -2.0,
+                lon: 3.0,
+            },
+            Point { lat: 4.0, lon: 4.0 },
+        ];
+        assert_eq!(
+            Some(BoundingBox::new(4.0, -2.0, 5.0, 3.0)),
+            BoundingBox::from_points(points)
+        );
+
+        // Fiji, on both sides of the antimeridian.
+        let fiji = [
+            Point {
+                lat: -18.1,
+                lon: 178.4,
+            },
+            Point {
+                lat: -16.8,
+                lon: -179.9,
+            },
+            Point {
+                lat: -17.8,
+                lon: 177.4,
+            },
+        ];
+        let bbox = BoundingBox::from_points(fiji).unwrap();
+        assert_eq!(BoundingBox::new(-16.8, -18.1, -179.9, 177.4), bbox);
+        assert!(bbox.contains(-17.0, 180.0));
+        assert!(!bbox.contains(-17.0, 0.0));
+    }
+
+    #[test]
+    fn test_centroid() {
+        assert_eq!(None, centroid(&[]));
+
+        let c = centroid(&[Point { lat: 0.0, lon: 2.0 }, Point { lat: 2.0, lon: 4.0 }]).unwrap();
+        assert_eq!(Point { lat: 1.0, lon: 3.0 }, c);
+
+        let c = centroid(&[
+            Point {
+                lat: 0.0,
+                lon: 179.0,
+            },
+            Point {
+                lat: 0.0,
+                lon: -177.0,
+            },
+        ])
+        .unwrap();
+        assert!((c.lon - -179.0).abs() < 1e-9, "{:?}", c);
+    }
+
+    #[test]
+    fn test_multipolygon_contains() {
+        let mp = MultiPolygon::from(vec![vec![square(0.0, 10.0), square(4.0, 6.0)]]);
+
+        assert!(mp.contains(1.0, 1.0));
+        assert!(mp.contains(9.0, 2.0));
+        assert!(!mp.contains(5.0, 5.0), "point inside the hole");
+        assert!(!mp.contains(11.0, 5.0));
+        assert!(!mp.contains(-1.0, -1.0));
+    }
+
+    #[test]
+    fn test_multipolygon_deserialise() {
+        let mp: MultiPolygon =
+            serde_json::from_str("[[[[0,0],[2,0],[2,3],[0,3],[0,0]]],[[[5,5],[6,5],[6,6],[5,5]]]]")
+                .unwrap();
+
+        assert_eq!(mp.bbox, [0.0, 0.0, 6.0, 6.0]);
+        assert!(mp.contains(2.5, 1.0));
+        assert!(mp.contains(5.2, 5.8));
+        assert!(!mp.contains(4.0, 4.0));
+    }
+}
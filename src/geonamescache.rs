@@ -1,15 +1,55 @@
+use std::borrow::Cow;
+#[cfg(any(feature = "country-polygons", feature = "timezone-polygons"))]
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
 use std::sync::Mutex;
 use std::{collections::HashMap, sync::Arc};
 
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::aliases::CountryAliases;
+use crate::compact::CompactCities;
+#[cfg(any(feature = "timezone-polygons", feature = "country-polygons"))]
+use crate::geometry::MultiPolygon;
+use crate::geometry::{haversine_km, is_valid_coordinate, BoundingBox};
+#[cfg(feature = "alternate-names")]
+use crate::geotypes::AlternateName;
 use crate::geotypes::{City, Continent, Country, UsCounty, UsState};
+use crate::location::{
+    build_city_name_index, disambiguate_city, parse_location, CityCandidate, CityHints, NearestCity,
+};
+use crate::mappers::{CountryMapper, UnknownField};
+use crate::resolve::{CountryBatch, CountryIndex, CountryMatch};
+
+/// Countries of a continent and their totals, precomputed from the countries dataset.
+#[derive(Default)]
+struct ContinentMembers {
+    countries: Vec<String>,
+    population: u64,
+    areakm2: u64,
+}
 
+/// Each dataset is parsed the first time it is used, so e.g. country lookups never
+/// load the cities. Call [`Geonamescache::preload_all`] to pay that cost up front.
 pub struct Geonamescache {
-    us_states: HashMap<String, UsState>,
-    continents: HashMap<String, Continent>,
-    countries: HashMap<String, Country>,
-    cities: HashMap<String, City>,
-    us_counties: Vec<UsCounty>,
+    us_states: OnceCell<HashMap<String, UsState>>,
+    continents: OnceCell<HashMap<String, Continent>>,
+    countries: OnceCell<HashMap<String, Country>>,
+    country_index: OnceCell<CountryIndex>,
+    continent_members: OnceCell<HashMap<String, ContinentMembers>>,
+    cities: OnceCell<HashMap<String, City>>,
+    compact_cities: OnceCell<CompactCities<'static>>,
+    us_counties: OnceCell<Vec<UsCounty>>,
     cities_by_name_cache: Mutex<HashMap<String, Arc<HashMap<String, City>>>>,
+    city_name_index: OnceCell<HashMap<String, Vec<String>>>,
+    country_aliases: Arc<CountryAliases>,
+    #[cfg(feature = "alternate-names")]
+    alternate_names: OnceCell<HashMap<u32, Vec<AlternateName>>>,
+    #[cfg(feature = "timezone-polygons")]
+    timezones: OnceCell<BTreeMap<String, MultiPolygon>>,
+    #[cfg(feature = "country-polygons")]
+    country_boundaries: OnceCell<BTreeMap<String, MultiPolygon>>,
 }
 
 macro_rules! load_data {
@@ -22,20 +62,121 @@ macro_rules! load_data {
 impl Geonamescache {
     pub fn new() -> Geonamescache {
         Self {
-            us_states: load_us_states(),
-            continents: load_data!("continents.json"),
-            countries: load_data!("countries.json"),
-            cities: load_data!("cities.json"),
-            us_counties: load_data!("us_counties.json"),
+            us_states: OnceCell::new(),
+            continents: OnceCell::new(),
+            countries: OnceCell::new(),
+            country_index: OnceCell::new(),
+            continent_members: OnceCell::new(),
+            cities: OnceCell::new(),
+            compact_cities: OnceCell::new(),
+            us_counties: OnceCell::new(),
             cities_by_name_cache: Mutex::new(HashMap::new()),
+            city_name_index: OnceCell::new(),
+            country_aliases: Arc::default(),
+            #[cfg(feature = "alternate-names")]
+            alternate_names: OnceCell::new(),
+            #[cfg(feature = "timezone-polygons")]
+            timezones: OnceCell::new(),
+            #[cfg(feature = "country-polygons")]
+            country_boundaries: OnceCell::new(),
         }
     }
 
+    /// Create a cache that uses the given cities, keyed by geonameid, instead of the
+    /// bundled dataset. This is how cities are provided when the crate is built
+    /// without the `embedded-cities` feature, e.g. to keep a WebAssembly binary small.
+    pub fn with_cities(cities: HashMap<String, City>) -> Geonamescache {
+        let mut gc = Self::new();
+        let cities = gc.attach_city_names(cities);
+        gc.cities = OnceCell::with_value(cities);
+        gc
+    }
+
+    /// The instance shared by the whole process, created on first use. The free
+    /// functions such as [`crate::countries`] read from it.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::Geonamescache;
+    /// let gc = Geonamescache::global();
+    /// assert!(std::ptr::eq(gc, Geonamescache::global()));
+    /// assert_eq!(gc.get_countries()["FR"].name, "France");
+    /// ```
+    pub fn global() -> &'static Geonamescache {
+        static GLOBAL: Lazy<Geonamescache> = Lazy::new(Geonamescache::new);
+        &GLOBAL
+    }
+
+    /// Load every dataset and index now rather than on first use, e.g. before a
+    /// server starts accepting requests.
+    pub fn preload_all(&self) {
+        self.get_us_states();
+        self.get_continents();
+        self.get_countries();
+        self.country_index();
+        self.continent_members();
+        self.get_cities();
+        self.get_compact_cities();
+        self.get_us_counties();
+        self.city_name_index();
+        #[cfg(feature = "timezone-polygons")]
+        self.timezones();
+        #[cfg(feature = "country-polygons")]
+        self.country_boundaries();
+    }
+
     pub fn get_continents(&self) -> &HashMap<String, Continent> {
-        &self.continents
+        self.continents.get_or_init(|| {
+            crate::tables::CONTINENTS
+                .iter()
+                .map(|c| (c.code.to_string(), Continent::from(c)))
+                .collect()
+        })
     }
+
     pub fn get_countries(&self) -> &HashMap<String, Country> {
-        &self.countries
+        self.countries.get_or_init(|| {
+            #[allow(unused_mut)]
+            let mut countries: HashMap<String, Country> = crate::tables::COUNTRIES
+                .iter()
+                .map(|c| (c.iso.to_string(), Country::from(c)))
+                .collect();
+            #[cfg(feature = "alternate-names")]
+            for country in countries.values_mut() {
+                country.localized_names = self.localized_names(country.geonameid);
+            }
+            countries
+        })
+    }
+
+    fn country_index(&self) -> &CountryIndex {
+        self.country_index
+            .get_or_init(|| CountryIndex::new(self.get_countries()))
+    }
+
+    fn continent_members(&self) -> &HashMap<String, ContinentMembers> {
+        self.continent_members
+            .get_or_init(|| build_continent_members(self.get_countries()))
+    }
+
+    #[cfg(feature = "alternate-names")]
+    fn localized_names(&self, geonameid: u32) -> Vec<AlternateName> {
+        self.alternate_names
+            .get_or_init(|| load_data!("alternate_names.json"))
+            .get(&geonameid)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "timezone-polygons")]
+    fn timezones(&self) -> &BTreeMap<String, MultiPolygon> {
+        self.timezones.get_or_init(|| load_data!("timezones.json"))
+    }
+
+    #[cfg(feature = "country-polygons")]
+    fn country_boundaries(&self) -> &BTreeMap<String, MultiPolygon> {
+        self.country_boundaries
+            .get_or_init(|| load_data!("country_boundaries.json"))
     }
 
     pub fn get_countries_by_names(&self) -> HashMap<&str, &Country> {
@@ -45,23 +186,183 @@ impl Geonamescache {
             .collect()
     }
 
+    /// Create a map between two fields in Country that borrows from this cache. This is
+    /// done by taking a closure that returns a (key, value) pair for each country.
+    ///
+    /// Like [`crate::mappers::country`], the map is case-insensitive.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::Geonamescache;
+    /// let gc = Geonamescache::new();
+    /// let name_to_iso3 = gc.country_mapper(|c| (&c.name, &c.iso3));
+    /// assert_eq!(name_to_iso3.get("united kingdom"), Some("GBR"));
+    /// ```
+    pub fn country_mapper<'a, F, K, V>(&'a self, f: F) -> CountryMapper<'a>
+    where
+        F: Fn(&'a Country) -> (K, V),
+        K: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        let triples = self.get_countries().values().map(|c| {
+            let (k, v) = f(c);
+            (c, k, v)
+        });
+        CountryMapper::new(triples, self.country_aliases())
+    }
+
+    /// Like [`Geonamescache::country_mapper`], with the two fields given by name (see
+    /// [`Country::FIELDS`]), e.g. from a command-line option. The mapper owns its data,
+    /// so it can outlive the cache.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::Geonamescache;
+    /// let gc = Geonamescache::new();
+    /// let name_to_iso3 = gc.country_field_mapper("name", "iso3").unwrap();
+    /// assert_eq!(name_to_iso3.get("spain"), Some("ESP"));
+    /// assert!(gc.country_field_mapper("name", "iso4").is_err());
+    /// ```
+    pub fn country_field_mapper(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<CountryMapper<'static>, UnknownField> {
+        if let Some(field) = [from, to].iter().find(|f| !Country::FIELDS.contains(f)) {
+            return Err(UnknownField {
+                field: field.to_string(),
+            });
+        }
+        let triples = self.get_countries().values().filter_map(|c| {
+            let key = c.field(from)?.into_owned();
+            Some((c, key, c.field(to)?.into_owned()))
+        });
+        Ok(CountryMapper::new(triples, self.country_aliases()))
+    }
+
+    /// Register an alternative spelling of a country name, e.g. `"Türkiye"` for
+    /// `"Turkey"`. It is used by every name-based country lookup alongside the
+    /// built-in aliases, including by mappers that were already created.
+    pub fn add_country_alias(&self, alias: &str, name: &str) {
+        self.country_aliases.insert(alias, name);
+    }
+
+    /// Register the aliases in a JSON file containing an object that maps alternative
+    /// spellings to country names, e.g. `{"Türkiye": "Turkey"}`.
+    pub fn load_country_aliases<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.country_aliases.load(path)
+    }
+
+    /// Find the country a free-text value refers to. The value may be a name, an
+    /// alias, an ISO 3166 code (alpha-2, alpha-3 or numeric), a FIPS code or, with
+    /// the `alternate-names` feature, a localized name. All of them are
+    /// case-insensitive.
+    pub fn resolve_country(&self, query: &str) -> Option<&Country> {
+        self.resolve_country_match(query).map(|m| m.country)
+    }
+
+    /// Like [`Geonamescache::resolve_country`], but also reports which rule matched
+    /// and how confident the match is.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::{Geonamescache, MatchRule};
+    /// let gc = Geonamescache::new();
+    /// let m = gc.resolve_country_match("DEU").unwrap();
+    /// assert_eq!(m.country.name, "Germany");
+    /// assert_eq!(m.rule, MatchRule::Iso3);
+    /// ```
+    pub fn resolve_country_match(&self, query: &str) -> Option<CountryMatch<'_>> {
+        self.country_index()
+            .resolve(self.get_countries(), &self.country_aliases, query)
+    }
+
+    /// Resolve many free-text values at once, e.g. a column of a CSV file, with the
+    /// same rules as [`Geonamescache::resolve_country`]. Each distinct value is only
+    /// resolved once. Values that don't match any country are reported with the names
+    /// of the countries spelled most similarly, to help grow the alias table.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::Geonamescache;
+    /// let gc = Geonamescache::new();
+    /// let batch = gc.resolve_countries(["France", "FRA", "Frnace", "Frnace"]);
+    /// assert_eq!(batch.countries[1].map(|c| c.iso.as_str()), Some("FR"));
+    /// assert_eq!(batch.unmatched[0].value, "Frnace");
+    /// assert_eq!(batch.unmatched[0].count, 2);
+    /// assert_eq!(batch.unmatched[0].suggestions[0], "France");
+    /// ```
+    pub fn resolve_countries<I, S>(&self, values: I) -> CountryBatch<'_>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.country_index()
+            .resolve_batch(self.get_countries(), &self.country_aliases, values)
+    }
+
+    pub(crate) fn country_aliases(&self) -> Arc<CountryAliases> {
+        Arc::clone(&self.country_aliases)
+    }
+
     pub fn get_us_states(&self) -> &HashMap<String, UsState> {
-        &self.us_states
+        self.us_states.get_or_init(load_us_states)
     }
 
+    /// The bundled cities or, for a cache created with
+    /// [`Geonamescache::with_cities`], the given ones. Without the `embedded-cities`
+    /// feature there are no bundled cities.
     pub fn get_cities(&self) -> &HashMap<String, City> {
-        &self.cities
+        self.cities
+            .get_or_init(|| self.attach_city_names(load_cities()))
+    }
+
+    #[cfg(feature = "alternate-names")]
+    fn attach_city_names(&self, mut cities: HashMap<String, City>) -> HashMap<String, City> {
+        for city in cities.values_mut() {
+            city.localized_names = self.localized_names(city.geonameid);
+        }
+        cities
+    }
+
+    #[cfg(not(feature = "alternate-names"))]
+    fn attach_city_names(&self, cities: HashMap<String, City>) -> HashMap<String, City> {
+        cities
+    }
+
+    /// The same cities as [`Geonamescache::get_cities`] in the [`CompactCities`]
+    /// layout. Unless the cities were already loaded or given to
+    /// [`Geonamescache::with_cities`], the bundled dataset is read straight into it,
+    /// without building the [`City`] records.
+    pub fn get_compact_cities(&self) -> &CompactCities<'static> {
+        self.compact_cities.get_or_init(|| match self.cities.get() {
+            Some(cities) => CompactCities::from_cities(cities.values()),
+            None => self.load_compact_cities(),
+        })
+    }
+
+    #[cfg(all(feature = "embedded-cities", not(feature = "alternate-names")))]
+    fn load_compact_cities(&self) -> CompactCities<'static> {
+        CompactCities::embedded()
+    }
+
+    /// The localized names are only attached to the [`City`] records.
+    #[cfg(any(not(feature = "embedded-cities"), feature = "alternate-names"))]
+    fn load_compact_cities(&self) -> CompactCities<'static> {
+        CompactCities::from_cities(self.get_cities().values())
     }
 
     pub fn get_us_counties(&self) -> &Vec<UsCounty> {
-        &self.us_counties
+        self.us_counties
+            .get_or_init(|| load_data!("us_counties.json"))
     }
 
+    /// Looked up in the compact store, so that the [`City`] records are only built for
+    /// the cities found.
     fn build_cities_by_name(&self, name: &str) -> HashMap<String, City> {
-        self.get_cities()
-            .iter()
-            .filter(|(_, city)| city.name.to_lowercase() == name.to_lowercase())
-            .map(|(gid, city)| (gid.clone(), city.clone()))
+        self.get_compact_cities()
+            .by_name(name)
+            .map(|city| (city.geonameid().to_string(), city.to_city()))
             .collect()
     }
 
@@ -73,44 +374,313 @@ impl Geonamescache {
         Arc::clone(m)
     }
 
+    /// Geonameids of the cities with the given name or alternate name
+    /// (case-insensitive).
+    pub(crate) fn city_ids_by_name(&self, name: &str) -> &[String] {
+        self.city_name_index()
+            .get(name.trim().to_lowercase().as_str())
+            .map_or(&[], |ids| ids.as_slice())
+    }
+
+    fn city_name_index(&self) -> &HashMap<String, Vec<String>> {
+        self.city_name_index
+            .get_or_init(|| build_city_name_index(self.get_cities()))
+    }
+
+    /// Find the cities a free-text location such as "Springfield, OH", "Paris, France"
+    /// or "London UK" may refer to. The text after the city name may be a country, a
+    /// US state or an admin1 code.
+    ///
+    /// Every city with that name is returned: first those matching the most of the
+    /// country and region parts, then the most populous.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::Geonamescache;
+    /// let gc = Geonamescache::new();
+    /// let cities = gc.parse_location("Springfield, OH");
+    /// assert_eq!(cities[0].admin1code, "OH");
+    /// ```
+    pub fn parse_location(&self, text: &str) -> Vec<&City> {
+        parse_location(self, text)
+    }
+
+    /// Rank the cities with the given name (or alternate name) by how well they fit the
+    /// hints, best first. Population breaks ties, then the geonameid, so the order is
+    /// always the same.
+    ///
+    /// # Examples
+    /// ```
+    /// use geonamescache::{CityHints, Geonamescache};
+    /// let gc = Geonamescache::new();
+    /// let hints = CityHints {
+    ///     country: Some("Colombia".into()),
+    ///     ..Default::default()
+    /// };
+    /// let madrid = &gc.disambiguate_city("Madrid", &hints)[0];
+    /// assert_eq!(madrid.city.countrycode, "CO");
+    /// ```
+    pub fn disambiguate_city(&self, name: &str, hints: &CityHints) -> Vec<CityCandidate<'_>> {
+        disambiguate_city(self, name, hints)
+    }
+
+    /// Cities with an alternate name equal to the query (case-insensitive), most
+    /// populous first, then by geonameid.
     pub fn search_cities(&self, query: &str) -> Vec<&City> {
         let query = query.to_lowercase();
-        self.get_cities()
+        let mut cities: Vec<&City> = self
+            .get_cities()
             .values()
             .filter(|geo| geo.alternatenames.iter().any(|x| x.to_lowercase() == query))
-            .collect()
+            .collect();
+        cities.sort_by(|a, b| {
+            b.population
+                .cmp(&a.population)
+                .then(a.geonameid.cmp(&b.geonameid))
+        });
+        cities
+    }
+
+    /// Find the city closest to the given coordinates, by great-circle distance, or
+    /// `None` if the coordinates aren't valid (see [`is_valid_coordinate`]).
+    ///
+    /// There is no spatial index: every call computes the distance to every loaded city,
+    /// tens of thousands with the bundled dataset. Fine for occasional lookups; callers
+    /// resolving many points should build their own index.
+    pub fn nearest_city(&self, lat: f64, lon: f64) -> Option<&City> {
+        self.nearest_city_with_distance(lat, lon).map(|n| n.city)
+    }
+
+    /// Like [`Geonamescache::nearest_city`], with the distance to the city in km.
+    pub fn nearest_city_with_distance(&self, lat: f64, lon: f64) -> Option<NearestCity<'_>> {
+        if !is_valid_coordinate(lat, lon) {
+            return None;
+        }
+        self.get_cities()
+            .values()
+            .map(|city| NearestCity {
+                city,
+                distance_km: haversine_km(lat, lon, city.latitude, city.longitude),
+            })
+            .min_by(|a, b| a.distance_km.total_cmp(&b.distance_km))
+    }
+
+    /// Get the IANA timezone for arbitrary coordinates.
+    ///
+    /// With the `timezone-polygons` feature the point is first tested against the
+    /// timezone boundaries, in timezone name order so that a point on a shared
+    /// boundary always gets the same one. Otherwise, or if the point falls outside all
+    /// of them (e.g. at sea), the timezone of the nearest city is used. Invalid
+    /// coordinates have no timezone.
+    pub fn timezone_at(&self, lat: f64, lon: f64) -> Option<&str> {
+        if !is_valid_coordinate(lat, lon) {
+            return None;
+        }
+        #[cfg(feature = "timezone-polygons")]
+        if let Some((tz, _)) = self.timezones().iter().find(|(_, p)| p.contains(lat, lon)) {
+            return Some(tz);
+        }
+
+        self.nearest_city(lat, lon).map(|c| c.timezone.as_str())
+    }
+
+    /// Countries on the continent with the given code (e.g. `"EU"`), ordered by ISO code.
+    pub fn countries_in_continent(&self, continent_code: &str) -> Vec<&Country> {
+        let countries = self.get_countries();
+        self.continent_members()
+            .get(continent_code)
+            .map(|m| m.countries.iter().map(|iso| &countries[iso]).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn continent_of_country(&self, iso: &str) -> Option<&Continent> {
+        let country = self.get_countries().get(iso)?;
+        self.get_continents().get(&country.continentcode)
+    }
+
+    pub fn continent_of_city(&self, city: &City) -> Option<&Continent> {
+        self.continent_of_country(&city.countrycode)
+    }
+
+    /// Total population of the countries on a continent.
+    pub fn continent_population(&self, continent_code: &str) -> Option<u64> {
+        self.continent_members()
+            .get(continent_code)
+            .map(|m| m.population)
+    }
+
+    /// Total area in km² of the countries on a continent.
+    pub fn continent_area(&self, continent_code: &str) -> Option<u64> {
+        self.continent_members()
+            .get(continent_code)
+            .map(|m| m.areakm2)
+    }
+
+    /// Countries whose bounding box intersects `bbox`, ordered by ISO code.
+    pub fn countries_in_bbox(&self, bbox: &BoundingBox) -> Vec<&Country> {
+        let mut countries: Vec<&Country> = self
+            .get_countries()
+            .values()
+            .filter(|c| c.bbox.is_some_and(|b| b.intersects(bbox)))
+            .collect();
+        countries.sort_by(|a, b| a.iso.cmp(&b.iso));
+        countries
+    }
+
+    /// Cities located inside `bbox`, ordered by geonameid.
+    pub fn cities_in_bbox(&self, bbox: &BoundingBox) -> Vec<&City> {
+        let mut cities: Vec<&City> = self
+            .get_cities()
+            .values()
+            .filter(|c| bbox.contains(c.latitude, c.longitude))
+            .collect();
+        cities.sort_by_key(|c| c.geonameid);
+        cities
+    }
+
+    /// Find the country whose boundary contains the given coordinates. Boundaries are
+    /// tested in ISO code order, so a point on a border shared by several countries
+    /// always resolves to the same one.
+    #[cfg(feature = "country-polygons")]
+    pub fn country_at(&self, lat: f64, lon: f64) -> Option<&Country> {
+        self.country_boundaries()
+            .iter()
+            .find(|(_, boundary)| boundary.contains(lat, lon))
+            .and_then(|(iso, _)| self.get_countries().get(iso))
     }
 }
 
-impl<'a> Default for Geonamescache {
+impl Default for Geonamescache {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(not(feature = "embedded-cities"))]
+fn load_cities() -> HashMap<String, City> {
+    HashMap::new()
+}
+
+/// Continents of the [global](Geonamescache::global) cache.
+pub fn continents() -> &'static HashMap<String, Continent> {
+    Geonamescache::global().get_continents()
+}
+
+/// Countries of the [global](Geonamescache::global) cache.
+///
+/// # Examples
+/// ```
+/// assert_eq!(geonamescache::countries()["DE"].iso3, "DEU");
+/// ```
+pub fn countries() -> &'static HashMap<String, Country> {
+    Geonamescache::global().get_countries()
+}
+
+/// US states of the [global](Geonamescache::global) cache.
+pub fn us_states() -> &'static HashMap<String, UsState> {
+    Geonamescache::global().get_us_states()
+}
+
+/// Cities of the [global](Geonamescache::global) cache.
+pub fn cities() -> &'static HashMap<String, City> {
+    Geonamescache::global().get_cities()
+}
+
+/// US counties of the [global](Geonamescache::global) cache.
+pub fn us_counties() -> &'static [UsCounty] {
+    Geonamescache::global().get_us_counties()
+}
+
 pub fn load_us_states() -> HashMap<String, UsState> {
-    crate::data::US_STATES
+    crate::tables::US_STATES
         .iter()
-        .map(|(k, v)| {
-            let v2 = UsState {
-                code: v.get("name").unwrap().to_string(),
-                name: v.get("name").unwrap().to_string(),
-                fips: v.get("fips").unwrap().to_string(),
-                geonameid: v.get("geonameid").unwrap().parse().unwrap(),
-            };
-            (k.to_string(), v2)
-        })
+        .map(|s| (s.code.to_string(), UsState::from(s)))
+        .collect()
+}
+
+#[cfg(all(feature = "embedded-cities", not(feature = "snapshot")))]
+fn load_cities() -> HashMap<String, City> {
+    load_data!("cities.json")
+}
+
+/// Build the city records from the embedded snapshot instead of parsing `cities.json`.
+#[cfg(feature = "snapshot")]
+fn load_cities() -> HashMap<String, City> {
+    CompactCities::embedded()
+        .iter()
+        .map(|c| (c.geonameid().to_string(), c.to_city()))
         .collect()
 }
 
+fn build_continent_members(
+    countries: &HashMap<String, Country>,
+) -> HashMap<String, ContinentMembers> {
+    let mut members: HashMap<String, ContinentMembers> = HashMap::new();
+    for country in countries.values() {
+        let m = members.entry(country.continentcode.clone()).or_default();
+        m.countries.push(country.iso.clone());
+        m.population += u64::from(country.population);
+        m.areakm2 += u64::from(country.areakm2);
+    }
+    for m in members.values_mut() {
+        m.countries.sort();
+    }
+    members
+}
+
 #[cfg(test)]
 mod tests {
-    use more_asserts::assert_ge;
+    use more_asserts::{assert_ge, assert_lt};
     use once_cell::sync::Lazy;
 
     use super::*;
+    use crate::geometry::Point;
+    use crate::MatchRule;
+
+    static GC: Lazy<Geonamescache> = Lazy::new(Geonamescache::new);
+
+    #[test]
+    fn test_lazy_loading() {
+        let gc = Geonamescache::new();
+        assert_eq!(gc.get_continents().len(), 7);
+        assert_eq!(gc.get_us_states().len(), 51);
+        assert!(gc.cities.get().is_none());
+        assert!(gc.countries.get().is_none());
+
+        gc.preload_all();
+        assert!(gc.cities.get().is_some());
+        assert!(gc.country_index.get().is_some());
+        assert!(gc.city_name_index.get().is_some());
+    }
 
-    static GC: Lazy<Geonamescache> = Lazy::new(|| Geonamescache::new());
+    #[test]
+    fn test_with_cities() {
+        let samobor = GC.get_cities()["3191316"].clone();
+        let gc = Geonamescache::with_cities(HashMap::from([("3191316".to_string(), samobor)]));
+
+        assert_eq!(gc.get_cities().len(), 1);
+        assert_eq!(gc.nearest_city(48.8, 2.3).unwrap().name, "Samobor");
+    }
+
+    #[test]
+    fn test_compact_cities() {
+        let gc = Geonamescache::new();
+        let madrids = gc.get_cities_by_name("madrid");
+        assert_eq!(madrids.len(), 2);
+        #[cfg(not(feature = "alternate-names"))]
+        assert!(gc.cities.get().is_none());
+        for (gid, city) in madrids.iter() {
+            assert_eq!(city, &GC.get_cities()[gid]);
+        }
+        // The embedded snapshot is read in place.
+        #[cfg(all(feature = "snapshot", not(feature = "alternate-names")))]
+        assert_eq!(gc.get_compact_cities().heap_size(), 0);
+
+        let samobor = GC.get_cities()["3191316"].clone();
+        let gc = Geonamescache::with_cities(HashMap::from([("3191316".to_string(), samobor)]));
+        assert_eq!(gc.get_compact_cities().len(), 1);
+        assert_eq!(gc.get_cities_by_name("Samobor").len(), 1);
+    }
 
     #[test]
     fn test_continents() {
@@ -168,6 +738,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_us_state_codes() {
+        // States are keyed and coded by postal abbreviation, never by name.
+        for (code, state) in GC.get_us_states() {
+            assert_eq!(code, &state.code);
+            assert_eq!(2, state.code.len(), "{}", state.name);
+        }
+        assert_eq!("Illinois", GC.get_us_states()["IL"].name);
+    }
+
     #[test]
     fn test_get_countries_by_names() {
         let countries = GC.get_countries();
@@ -215,9 +795,424 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_search_cities_order() {
+        let cities = GC.search_cities("madrid");
+        assert_eq!(cities[0].geonameid, 3117735);
+        assert!(cities
+            .windows(2)
+            .all(|w| (w[1].population, w[0].geonameid) <= (w[0].population, w[1].geonameid)));
+    }
+
     #[test]
     fn test_us_counties_len() {
         let us_counties = GC.get_us_counties();
         assert_ge!(3234, us_counties.len());
     }
+
+    #[test]
+    fn test_parse_location() {
+        let test_data = [
+            ("Springfield, OH", "4525353"),
+            ("Springfield, Missouri", "4409896"),
+            ("springfield, il, USA", "4250542"),
+            ("Paris, France", "2988507"),
+            ("Paris, TX", "4717560"),
+            ("Paris Texas", "4717560"),
+            ("London UK", "2643743"),
+            ("London, Canada", "6058560"),
+            ("London United Kingdom", "2643743"),
+            ("New York", "5128581"),
+            ("Kiev", "703448"),
+        ];
+        for (text, gid) in test_data {
+            let cities = GC.parse_location(text);
+            assert_eq!(gid, cities[0].geonameid.to_string(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_location_population_tiebreak() {
+        let cities = GC.parse_location("Springfield");
+        assert_ge!(cities.len(), 4);
+        assert!(cities
+            .windows(2)
+            .all(|w| w[0].population >= w[1].population));
+
+        // Unknown qualifiers don't remove candidates.
+        assert_eq!(cities.len(), GC.parse_location("Springfield, Narnia").len());
+    }
+
+    #[test]
+    fn test_parse_location_unknown() {
+        assert!(GC.parse_location("Atlantis").is_empty());
+        assert!(GC.parse_location("").is_empty());
+        assert!(GC.parse_location(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_disambiguate_city() {
+        let hints =
+            |country: Option<&str>, admin1: Option<&str>, timezone: Option<&str>| CityHints {
+                country: country.map(Into::into),
+                admin1: admin1.map(Into::into),
+                near: None,
+                timezone: timezone.map(Into::into),
+            };
+        let test_data = [
+            ("Madrid", hints(None, None, None), "3117735"),
+            ("Madrid", hints(Some("CO"), None, None), "3675707"),
+            (
+                "Madrid",
+                hints(None, None, Some("America/Bogota")),
+                "3675707",
+            ),
+            ("Springfield", hints(None, Some("Ohio"), None), "4525353"),
+            (
+                "Springfield",
+                hints(Some("USA"), Some("MA"), None),
+                "4951788",
+            ),
+            (
+                "Springfield",
+                hints(None, None, Some("America/Chicago")),
+                "4409896",
+            ),
+        ];
+        for (name, hints, gid) in test_data {
+            let candidates = GC.disambiguate_city(name, &hints);
+            assert_eq!(gid, candidates[0].city.geonameid.to_string(), "{:?}", hints);
+            assert!(candidates.windows(2).all(|w| w[0].score >= w[1].score));
+        }
+    }
+
+    #[test]
+    fn test_disambiguate_city_population_tiebreak() {
+        // Without hints every Springfield scores 0 and the largest comes first.
+        let candidates = GC.disambiguate_city("Springfield", &CityHints::default());
+        assert_ge!(candidates.len(), 4);
+        assert!(candidates.iter().all(|c| c.score == 0.0));
+        assert!(candidates
+            .windows(2)
+            .all(|w| w[0].city.population >= w[1].city.population));
+    }
+
+    #[test]
+    fn test_disambiguate_city_near() {
+        // Dayton, OH.
+        let hints = CityHints {
+            near: Some(Point {
+                lat: 39.76,
+                lon: -84.19,
+            }),
+            ..Default::default()
+        };
+        let candidates = GC.disambiguate_city("Springfield", &hints);
+        assert_eq!("OH", candidates[0].city.admin1code);
+
+        assert!(GC.disambiguate_city("Atlantis", &hints).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_city() {
+        let city = GC.nearest_city(40.42, -3.70).unwrap();
+        assert_eq!("Madrid", city.name);
+        assert_eq!("ES", city.countrycode);
+
+        let nearest = GC.nearest_city_with_distance(40.42, -3.70).unwrap();
+        assert_eq!(nearest.city, city);
+        assert!(nearest.distance_km < 1.0, "{}", nearest.distance_km);
+
+        for (lat, lon) in [
+            (f64::NAN, 0.0),
+            (0.0, f64::INFINITY),
+            (91.0, 0.0),
+            (0.0, -181.0),
+        ] {
+            assert!(GC.nearest_city_with_distance(lat, lon).is_none());
+            assert!(GC.nearest_city(lat, lon).is_none());
+            assert!(GC.timezone_at(lat, lon).is_none());
+        }
+    }
+
+    #[test]
+    fn test_timezone_at() {
+        let test_data = [
+            (40.42, -3.70, "Europe/Madrid"),
+            (25.78, -80.2, "America/New_York"),
+            (35.68, 139.7, "Asia/Tokyo"),
+        ];
+        for (lat, lon, tz) in test_data {
+            assert_eq!(Some(tz), GC.timezone_at(lat, lon));
+        }
+    }
+
+    #[test]
+    fn test_resolve_country() {
+        let test_data = [
+            ("Germany", MatchRule::Name),
+            ("germany", MatchRule::Name),
+            ("DE", MatchRule::Iso),
+            ("deu", MatchRule::Iso3),
+            ("276", MatchRule::IsoNumeric),
+            (" Germany ", MatchRule::Name),
+        ];
+        for (query, rule) in test_data {
+            let m = GC.resolve_country_match(query).unwrap();
+            assert_eq!("DE", m.country.iso, "{}", query);
+            assert_eq!(rule, m.rule, "{}", query);
+            assert_eq!(rule.confidence(), m.confidence);
+        }
+
+        let m = GC.resolve_country_match("Viet Nam").unwrap();
+        assert_eq!(("VN", MatchRule::Alias), (m.country.iso.as_str(), m.rule));
+
+        // Numeric codes may be zero-padded.
+        assert_eq!("AT", GC.resolve_country("040").unwrap().iso);
+        for code in ["0", "00", "000", "+276", "0276"] {
+            assert!(GC.resolve_country(code).is_none(), "{}", code);
+        }
+
+        assert!(GC.resolve_country("Atlantis").is_none());
+        assert!(GC.resolve_country("").is_none());
+    }
+
+    #[test]
+    fn test_resolve_country_fips() {
+        // "GM" is the ISO code of Gambia and the FIPS code of Germany.
+        assert_eq!("GM", GC.resolve_country("GM").unwrap().iso);
+
+        // "UK" is only a FIPS code.
+        let m = GC.resolve_country_match("UK").unwrap();
+        assert_eq!(("GB", MatchRule::Fips), (m.country.iso.as_str(), m.rule));
+        assert_lt!(m.confidence, MatchRule::Iso.confidence());
+    }
+
+    #[test]
+    fn test_resolve_countries() {
+        let values = [
+            "Germany",
+            "DEU",
+            "Untied States",
+            "germany",
+            "Untied States",
+            "Foo",
+        ];
+        let batch = GC.resolve_countries(values.iter());
+
+        let isos: Vec<_> = batch
+            .countries
+            .iter()
+            .map(|c| c.map(|c| c.iso.as_str()))
+            .collect();
+        assert_eq!(
+            vec![Some("DE"), Some("DE"), None, Some("DE"), None, None],
+            isos
+        );
+
+        assert_eq!(2, batch.unmatched.len());
+        assert_eq!("Untied States", batch.unmatched[0].value);
+        assert_eq!(2, batch.unmatched[0].count);
+        assert_eq!("United States", batch.unmatched[0].suggestions[0]);
+        assert_eq!("Foo", batch.unmatched[1].value);
+        assert_eq!(1, batch.unmatched[1].count);
+    }
+
+    #[test]
+    fn test_resolve_countries_suggests_aliases() {
+        let gc = Geonamescache::new();
+        gc.add_country_alias("Türkiye", "Turkey");
+        let batch = gc.resolve_countries(vec!["Turkiye".to_string(), "Vet Nam".to_string()]);
+
+        let suggestions: Vec<_> = batch
+            .unmatched
+            .iter()
+            .map(|u| u.suggestions[0].as_str())
+            .collect();
+        assert!(suggestions.contains(&"Turkey"));
+        assert!(suggestions.contains(&"Vietnam"));
+    }
+
+    #[cfg(feature = "alternate-names")]
+    #[test]
+    fn test_resolve_country_localized() {
+        let m = GC.resolve_country_match("Deutschland").unwrap();
+        assert_eq!("DE", m.country.iso);
+        assert_eq!(MatchRule::LocalizedName, m.rule);
+    }
+
+    #[test]
+    fn test_countries_in_continent() {
+        let europe = GC.countries_in_continent("EU");
+        assert!(europe.iter().any(|c| c.iso == "FR"));
+        assert!(europe.iter().all(|c| c.continentcode == "EU"));
+        assert!(europe.windows(2).all(|w| w[0].iso < w[1].iso));
+
+        assert!(GC.countries_in_continent("XX").is_empty());
+    }
+
+    #[test]
+    fn test_continent_of_country() {
+        let test_data = [("FR", "EU"), ("BR", "SA"), ("US", "NA"), ("JP", "AS")];
+        for (iso, continent) in test_data {
+            let c = GC.continent_of_country(iso).unwrap();
+            assert_eq!(continent, c.continent_code);
+        }
+        assert!(GC.continent_of_country("XX").is_none());
+    }
+
+    #[test]
+    fn test_continent_of_city() {
+        let tokyo = GC.get_cities().get("1850147").unwrap();
+        assert_eq!("Asia", GC.continent_of_city(tokyo).unwrap().name);
+    }
+
+    #[test]
+    fn test_continent_totals() {
+        let europe = GC.countries_in_continent("EU");
+        let population: u64 = europe.iter().map(|c| u64::from(c.population)).sum();
+        let area: u64 = europe.iter().map(|c| u64::from(c.areakm2)).sum();
+
+        assert_eq!(Some(population), GC.continent_population("EU"));
+        assert_eq!(Some(area), GC.continent_area("EU"));
+        assert_ge!(GC.continent_population("AS").unwrap(), 4_000_000_000);
+        assert_eq!(None, GC.continent_population("XX"));
+    }
+
+    #[test]
+    fn test_continent_localized_name() {
+        let europe = GC.get_continents().get("EU").unwrap();
+        assert_eq!(Some("Europa"), europe.localized_name("de"));
+        assert_eq!(Some("ヨーロッパ"), europe.localized_name("ja"));
+    }
+
+    #[cfg(feature = "alternate-names")]
+    #[test]
+    fn test_localized_names() {
+        let germany = GC.get_countries().get("DE").unwrap();
+        assert_eq!(Some("Deutschland"), germany.localized_name("de"));
+
+        let tokyo = GC.get_cities().get("1850147").unwrap();
+        assert_eq!(Some("東京"), tokyo.localized_name("ja"));
+    }
+
+    #[test]
+    fn test_continent_geometry() {
+        let europe = GC.get_continents().get("EU").unwrap();
+        assert!(europe
+            .bbox
+            .contains(europe.centroid.lat, europe.centroid.lon));
+        assert!(europe.bbox.contains(48.85, 2.35));
+    }
+
+    #[test]
+    fn test_countries_without_cities() {
+        let gc = Geonamescache::new();
+        assert!(gc.get_countries()["ES"].bbox.is_some());
+        gc.resolve_country("Spain");
+        gc.countries_in_continent("EU");
+        assert!(gc.cities.get().is_none());
+    }
+
+    #[test]
+    fn test_country_geometry() {
+        let spain = GC.get_countries().get("ES").unwrap();
+        let bbox = spain.bbox.unwrap();
+        let centroid = spain.centroid.unwrap();
+        assert!(bbox.contains(centroid.lat, centroid.lon));
+        assert!(bbox.contains(40.4165, -3.70256));
+    }
+
+    #[test]
+    fn test_countries_in_bbox() {
+        let iberia = BoundingBox::new(44.0, 36.0, 4.0, -10.0);
+        let countries = GC.countries_in_bbox(&iberia);
+        assert!(countries.iter().any(|c| c.iso == "ES"));
+        assert!(!countries.iter().any(|c| c.iso == "JP"));
+        assert!(countries.windows(2).all(|w| w[0].iso < w[1].iso));
+    }
+
+    #[test]
+    fn test_cities_in_bbox() {
+        // Central Spain, short of Portugal, Andorra and France.
+        let castile = BoundingBox::new(42.0, 38.0, 0.0, -6.5);
+        let cities = GC.cities_in_bbox(&castile);
+        assert!(cities.windows(2).all(|w| w[0].geonameid < w[1].geonameid));
+        for name in ["Madrid", "Rivas-Vaciamadrid"] {
+            assert!(
+                cities
+                    .iter()
+                    .any(|c| c.name == name && c.countrycode == "ES"),
+                "{}",
+                name
+            );
+        }
+        for name in ["Lisbon", "Porto", "Andorra la Vella", "Toulouse", "Paris"] {
+            assert!(!cities.iter().any(|c| c.name == name), "{}", name);
+        }
+    }
+
+    #[cfg(feature = "country-polygons")]
+    #[test]
+    fn test_country_at() {
+        let test_data = [
+            (40.42, -3.70, "ES"),
+            (48.85, 2.35, "FR"),
+            (52.52, 13.4, "DE"),
+        ];
+        for (lat, lon, iso) in test_data {
+            assert_eq!(Some(iso), GC.country_at(lat, lon).map(|c| c.iso.as_str()));
+        }
+
+        // Middle of the Atlantic.
+        assert_eq!(None, GC.country_at(0.0, -30.0));
+    }
+
+    #[cfg(feature = "country-polygons")]
+    #[test]
+    fn test_country_at_border() {
+        let square = |min: f64, max: f64| {
+            MultiPolygon::from(vec![vec![vec![
+                [min, min],
+                [max, min],
+                [max, max],
+                [min, max],
+                [min, min],
+            ]]])
+        };
+        let gc = Geonamescache::new();
+        let _ = gc.country_boundaries.set(BTreeMap::from([
+            ("FR".to_string(), square(0.0, 2.0)),
+            ("ES".to_string(), square(1.0, 3.0)),
+        ]));
+
+        for _ in 0..10 {
+            assert_eq!(Some("ES"), gc.country_at(1.5, 1.5).map(|c| c.iso.as_str()));
+        }
+        assert_eq!(Some("FR"), gc.country_at(0.5, 0.5).map(|c| c.iso.as_str()));
+    }
+
+    #[cfg(feature = "timezone-polygons")]
+    #[test]
+    fn test_timezone_at_boundary() {
+        let square = |min: f64, max: f64| {
+            MultiPolygon::from(vec![vec![vec![
+                [min, min],
+                [max, min],
+                [max, max],
+                [min, max],
+                [min, min],
+            ]]])
+        };
+        let gc = Geonamescache::new();
+        let _ = gc.timezones.set(BTreeMap::from([
+            ("Europe/Paris".to_string(), square(0.0, 2.0)),
+            ("Europe/Madrid".to_string(), square(1.0, 3.0)),
+        ]));
+
+        for _ in 0..10 {
+            assert_eq!(Some("Europe/Madrid"), gc.timezone_at(1.5, 1.5));
+        }
+        assert_eq!(Some("Europe/Paris"), gc.timezone_at(0.5, 0.5));
+    }
 }
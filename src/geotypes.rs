@@ -1,6 +1,12 @@
-use serde::Deserialize;
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{BoundingBox, Point};
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Timezone {
     pub gmt_offset: i8,
@@ -8,13 +14,37 @@ pub struct Timezone {
     pub time_zone_id: String,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AlternateName {
     pub name: String,
     pub lang: String,
+    #[serde(default)]
+    pub is_preferred_name: bool,
+    #[serde(default)]
+    pub is_short_name: bool,
+}
+
+/// Pick the name to display for `lang`: preferred names first and, among those, short
+/// names before full ones (e.g. "Deutschland" before "Bundesrepublik Deutschland").
+fn localized_name<'a>(names: &'a [AlternateName], lang: &str) -> Option<&'a str> {
+    names
+        .iter()
+        .filter(|n| n.lang == lang)
+        .min_by_key(|n| (!n.is_preferred_name, !n.is_short_name))
+        .map(|n| n.name.as_str())
+}
+
+/// Like [`localized_name`], but favouring short names over preferred ones.
+fn localized_short_name<'a>(names: &'a [AlternateName], lang: &str) -> Option<&'a str> {
+    names
+        .iter()
+        .filter(|n| n.lang == lang)
+        .min_by_key(|n| (!n.is_short_name, !n.is_preferred_name))
+        .map(|n| n.name.as_str())
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Continent {
     pub lng: String,
@@ -30,9 +60,15 @@ pub struct Continent {
     pub wikipedia_url: String,
     pub alternate_names: Vec<AlternateName>,
     pub cc2: Option<String>,
+    /// Zero for a dataset without bounding boxes.
+    #[serde(default)]
+    pub bbox: BoundingBox,
+    /// Parsed from `lat` and `lng` when the dataset is loaded.
+    #[serde(skip)]
+    pub centroid: Point,
 }
 
-#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+#[derive(Clone, PartialEq, Debug, Default, Deserialize, Serialize)]
 pub struct City {
     pub geonameid: u32,
     pub name: String,
@@ -43,16 +79,19 @@ pub struct City {
     pub timezone: String,
     pub admin1code: String,
     pub alternatenames: Vec<String>,
+    /// Names tagged by language. Only available with the `alternate-names` feature.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub localized_names: Vec<AlternateName>,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
 pub struct UsCounty {
     pub fips: String,
     pub name: String,
     pub state: String,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
 pub struct Country {
     pub geonameid: u32,
     pub name: String,
@@ -70,9 +109,18 @@ pub struct Country {
     pub phone: String,
     pub languages: String,
     pub neighbours: String,
+    /// From the country shapes or, without them, approximated from the country's
+    /// cities at build time. `None` for countries without any city.
+    #[serde(default)]
+    pub bbox: Option<BoundingBox>,
+    #[serde(default)]
+    pub centroid: Option<Point>,
+    /// Names tagged by language. Only available with the `alternate-names` feature.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub localized_names: Vec<AlternateName>,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize)]
 pub struct UsState {
     pub code: String,
     pub name: String,
@@ -80,6 +128,84 @@ pub struct UsState {
     pub geonameid: u32,
 }
 
+impl Continent {
+    /// Name of the continent in the given language (ISO 639 code, e.g. `"de"`).
+    pub fn localized_name(&self, lang: &str) -> Option<&str> {
+        localized_name(&self.alternate_names, lang)
+    }
+
+    pub fn localized_short_name(&self, lang: &str) -> Option<&str> {
+        localized_short_name(&self.alternate_names, lang)
+    }
+}
+
+impl Country {
+    /// Names accepted by [`Country::field`].
+    pub const FIELDS: &'static [&'static str] = &[
+        "geonameid",
+        "name",
+        "iso",
+        "iso3",
+        "isonumeric",
+        "fips",
+        "continentcode",
+        "capital",
+        "areakm2",
+        "population",
+        "tld",
+        "currencycode",
+        "currencyname",
+        "phone",
+        "languages",
+        "neighbours",
+    ];
+
+    /// Value of a field given by name, e.g. `"iso3"`, formatted as a string. For
+    /// bindings and command-line tools that select fields at runtime.
+    pub fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        let value = match name {
+            "geonameid" => self.geonameid.to_string().into(),
+            "name" => self.name.as_str().into(),
+            "iso" => self.iso.as_str().into(),
+            "iso3" => self.iso3.as_str().into(),
+            "isonumeric" => self.isonumeric.to_string().into(),
+            "fips" => self.fips.as_str().into(),
+            "continentcode" => self.continentcode.as_str().into(),
+            "capital" => self.capital.as_str().into(),
+            "areakm2" => self.areakm2.to_string().into(),
+            "population" => self.population.to_string().into(),
+            "tld" => self.tld.as_str().into(),
+            "currencycode" => self.currencycode.as_str().into(),
+            "currencyname" => self.currencyname.as_str().into(),
+            "phone" => self.phone.as_str().into(),
+            "languages" => self.languages.as_str().into(),
+            "neighbours" => self.neighbours.as_str().into(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Name of the country in the given language (ISO 639 code, e.g. `"de"`).
+    pub fn localized_name(&self, lang: &str) -> Option<&str> {
+        localized_name(&self.localized_names, lang)
+    }
+
+    pub fn localized_short_name(&self, lang: &str) -> Option<&str> {
+        localized_short_name(&self.localized_names, lang)
+    }
+}
+
+impl City {
+    /// Name of the city in the given language (ISO 639 code, e.g. `"ja"`).
+    pub fn localized_name(&self, lang: &str) -> Option<&str> {
+        localized_name(&self.localized_names, lang)
+    }
+
+    pub fn localized_short_name(&self, lang: &str) -> Option<&str> {
+        localized_short_name(&self.localized_names, lang)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -88,6 +214,28 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn test_records_are_eq() {
+        fn is_eq<T: Eq>() {}
+        is_eq::<Country>();
+        is_eq::<Continent>();
+    }
+
+    #[test]
+    fn test_country_field() {
+        let country = Country {
+            name: "France".to_string(),
+            iso3: "FRA".to_string(),
+            isonumeric: 250,
+            ..Default::default()
+        };
+        assert_eq!(country.field("name").as_deref(), Some("France"));
+        assert_eq!(country.field("iso3").as_deref(), Some("FRA"));
+        assert_eq!(country.field("isonumeric").as_deref(), Some("250"));
+        assert_eq!(country.field("bbox"), None);
+        assert!(Country::FIELDS.iter().all(|f| country.field(f).is_some()));
+    }
+
     #[test]
     fn deserialise_timezone() {
         let json = indoc! {r#"
@@ -123,7 +271,9 @@ mod test {
             an,
             AlternateName {
                 name: "아프리카".into(),
-                lang: "ko".into()
+                lang: "ko".into(),
+                is_preferred_name: false,
+                is_short_name: false,
             }
         );
     }
@@ -182,13 +332,16 @@ mod test {
                 alternate_names: vec![
                     AlternateName {
                         name: "아프리카".into(),
-                        lang: "ko".into()
+                        lang: "ko".into(),
+                        ..Default::default()
                     },
                     AlternateName {
                         name: "Aafrika".into(),
-                        lang: "et".into()
+                        lang: "et".into(),
+                        ..Default::default()
                     },
-                ]
+                ],
+                ..Default::default()
             }
         );
     }
@@ -250,7 +403,8 @@ mod test {
                     population: 20430,
                     timezone: "Europe/Andorra".into(),
                     admin1code: "07".into(),
-                    alternatenames: vec!["ALV".into(), "Ando-la-Vyey".into(), "Andora".into()]
+                    alternatenames: vec!["ALV".into(), "Ando-la-Vyey".into(), "Andora".into()],
+                    localized_names: vec![],
                 }
             )])
         );
@@ -303,9 +457,87 @@ mod test {
                     currencyname: "Euro".into(),
                     phone: "376".into(),
                     languages: "ca".into(),
-                    neighbours: "ES,FR".into()
+                    neighbours: "ES,FR".into(),
+                    bbox: None,
+                    centroid: None,
+                    localized_names: vec![],
                 }
             )])
         );
     }
+
+    #[test]
+    fn deserialise_country_geometry() {
+        let json = indoc! {r#"
+        {
+            "geonameid": 3041565,
+            "name": "Andorra",
+            "iso": "AD",
+            "iso3": "AND",
+            "isonumeric": 20,
+            "fips": "AN",
+            "continentcode": "EU",
+            "capital": "Andorra la Vella",
+            "areakm2": 468,
+            "population": 84000,
+            "tld": ".ad",
+            "currencycode": "EUR",
+            "currencyname": "Euro",
+            "phone": "376",
+            "languages": "ca",
+            "neighbours": "ES,FR",
+            "bbox": {"north": 42.66, "south": 42.43, "east": 1.79, "west": 1.41},
+            "centroid": {"lat": 42.54, "lon": 1.6}
+        }
+        "#};
+
+        let c: Country = serde_json::from_str(json).unwrap();
+        assert_eq!(c.bbox, Some(BoundingBox::new(42.66, 42.43, 1.79, 1.41)));
+        assert_eq!(
+            c.centroid,
+            Some(Point {
+                lat: 42.54,
+                lon: 1.6
+            })
+        );
+    }
+
+    #[test]
+    fn deserialise_alternate_name_flags() {
+        let json = r#"{"name": "Deutschland", "lang": "de", "isPreferredName": true, "isShortName": true}"#;
+
+        let an: AlternateName = serde_json::from_str(json).unwrap();
+        assert!(an.is_preferred_name);
+        assert!(an.is_short_name);
+    }
+
+    #[test]
+    fn localized_name_ranking() {
+        let name = |name: &str, lang: &str, is_preferred_name, is_short_name| AlternateName {
+            name: name.into(),
+            lang: lang.into(),
+            is_preferred_name,
+            is_short_name,
+        };
+        let country = Country {
+            localized_names: vec![
+                name("Germania", "it", false, false),
+                name("Deutschland", "de", true, true),
+                name("BRD", "de", false, true),
+                name("Bundesrepublik Deutschland", "de", true, false),
+                name("Deutsches Reich", "de", false, false),
+                name("RFA", "fr", false, true),
+                name("Allemagne", "fr", true, false),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(country.localized_name("de"), Some("Deutschland"));
+        assert_eq!(country.localized_short_name("de"), Some("Deutschland"));
+        assert_eq!(country.localized_name("fr"), Some("Allemagne"));
+        assert_eq!(country.localized_short_name("fr"), Some("RFA"));
+        assert_eq!(country.localized_name("it"), Some("Germania"));
+        assert_eq!(country.localized_short_name("it"), Some("Germania"));
+        assert_eq!(country.localized_name("ja"), None);
+    }
 }
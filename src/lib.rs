@@ -1,6 +1,42 @@
-mod geonamescache;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 mod data;
+#[cfg(feature = "std")]
+mod geonamescache;
 mod geotypes;
+#[cfg(feature = "std")]
 pub mod mappers;
 
-pub use crate::geonamescache::Geonamescache;
+#[cfg(feature = "std")]
+mod aliases;
+#[cfg(feature = "std")]
+mod compact;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod geometry;
+#[cfg(feature = "std")]
+mod location;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+mod resolve;
+#[cfg(feature = "std")]
+pub mod snapshot;
+pub mod tables;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "std")]
+pub use crate::compact::{CityView, CompactCities};
+#[cfg(feature = "std")]
+pub use crate::geonamescache::{
+    cities, continents, countries, us_counties, us_states, Geonamescache,
+};
+pub use crate::geotypes::{AlternateName, City, Continent, Country, Timezone, UsCounty, UsState};
+#[cfg(feature = "std")]
+pub use crate::location::{CityCandidate, CityHints, NearestCity};
+#[cfg(feature = "std")]
+pub use crate::resolve::{CountryBatch, CountryMatch, MatchRule, Unmatched};
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::geometry::{haversine_km, Point};
+use crate::geotypes::{City, Country};
+use crate::Geonamescache;
+
+/// Maximum number of words tried as the region or country of a location written
+/// without commas, e.g. "United Kingdom" in "London United Kingdom".
+const MAX_QUALIFIER_WORDS: usize = 3;
+
+/// Index from the lowercase name and alternate names of every city to their
+/// geonameids.
+pub(crate) fn build_city_name_index(
+    cities: &HashMap<String, City>,
+) -> HashMap<String, Vec<String>> {
+    let mut index: HashMap<String, Vec<String>> = HashMap::new();
+    for (gid, city) in cities {
+        let mut names: Vec<String> = city
+            .alternatenames
+            .iter()
+            .chain([&city.name])
+            .filter(|n| !n.is_empty())
+            .map(|n| n.to_lowercase())
+            .collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            index.entry(name).or_default().push(gid.clone());
+        }
+    }
+    index
+}
+
+/// A part of a location that narrows down the city, e.g. "OH" or "France". It is
+/// matched against every reading of the text: a country, a US state or an admin1
+/// code. When the text is both a country and a US state, e.g. "IL" (Israel and
+/// Illinois), "CA" (Canada and California) or "Georgia", cities in the state rank
+/// above cities in the country.
+struct Qualifier<'a> {
+    text: String,
+    country: Option<&'a Country>,
+    us_state: Option<&'a str>,
+}
+
+impl<'a> Qualifier<'a> {
+    fn new(gc: &'a Geonamescache, text: &str) -> Self {
+        Self {
+            country: gc.resolve_country(text),
+            ..Self::region(gc, text)
+        }
+    }
+
+    /// A qualifier that can only be a US state or an admin1 code.
+    fn region(gc: &'a Geonamescache, text: &str) -> Self {
+        let text = text.trim().to_lowercase();
+        let us_state = gc
+            .get_us_states()
+            .iter()
+            .find(|(code, s)| code.to_lowercase() == text || s.name.to_lowercase() == text)
+            .map(|(code, _)| code.as_str());
+        Self {
+            country: None,
+            us_state,
+            text,
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        self.country.is_some() || self.us_state.is_some()
+    }
+
+    fn matches(&self, city: &City) -> bool {
+        self.country.is_some_and(|c| c.iso == city.countrycode) || self.matches_region(city)
+    }
+
+    fn matches_region(&self, city: &City) -> bool {
+        self.us_state
+            .is_some_and(|s| city.countrycode == "US" && city.admin1code == s)
+            || city.admin1code.to_lowercase() == self.text
+    }
+}
+
+/// How well a city matches the qualifiers: the number it matches, then the number
+/// it matches as a region, so that a state wins over a country of the same name.
+fn rank(qualifiers: &[Qualifier], city: &City) -> (usize, usize) {
+    let matches = qualifiers.iter().filter(|q| q.matches(city)).count();
+    let regions = qualifiers.iter().filter(|q| q.matches_region(city)).count();
+    (matches, regions)
+}
+
+/// Split a location into the city name and its qualifiers.
+fn split<'a>(gc: &'a Geonamescache, text: &str) -> Option<(String, Vec<Qualifier<'a>>)> {
+    let parts: Vec<&str> = text
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    let (name, rest) = parts.split_first()?;
+    if !rest.is_empty() {
+        let qualifiers = rest.iter().map(|q| Qualifier::new(gc, q)).collect();
+        return Some((name.to_string(), qualifiers));
+    }
+
+    // Without commas, the qualifier is the shortest suffix of words that names a
+    // country or region, as long as the rest is the name of a city.
+    let words: Vec<&str> = name.split_whitespace().collect();
+    if gc.city_ids_by_name(name).is_empty() {
+        for n in (1..words.len())
+            .rev()
+            .filter(|&n| words.len() - n <= MAX_QUALIFIER_WORDS)
+        {
+            let (city, qualifier) = words.split_at(n);
+            let city = city.join(" ");
+            let qualifier = Qualifier::new(gc, &qualifier.join(" "));
+            if qualifier.is_known() && !gc.city_ids_by_name(&city).is_empty() {
+                return Some((city, vec![qualifier]));
+            }
+        }
+    }
+    Some((words.join(" "), Vec::new()))
+}
+
+pub(crate) fn parse_location<'a>(gc: &'a Geonamescache, text: &str) -> Vec<&'a City> {
+    let (name, qualifiers) = match split(gc, text) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+
+    let mut candidates: Vec<((usize, usize), &City)> = gc
+        .city_ids_by_name(&name)
+        .iter()
+        .map(|gid| &gc.get_cities()[gid])
+        .map(|city| (rank(&qualifiers, city), city))
+        .collect();
+    candidates.sort_by(|(sa, a), (sb, b)| {
+        sb.cmp(sa)
+            .then(b.population.cmp(&a.population))
+            .then(a.geonameid.cmp(&b.geonameid))
+    });
+    candidates.into_iter().map(|(_, city)| city).collect()
+}
+
+/// Context used by [`crate::Geonamescache::disambiguate_city`] to rank the cities
+/// sharing a name. All hints are optional.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct CityHints {
+    /// Country, in any form accepted by [`crate::Geonamescache::resolve_country`].
+    pub country: Option<String>,
+    /// Admin1 code, or US state name or code.
+    pub admin1: Option<String>,
+    /// A point the city is expected to be close to.
+    pub near: Option<Point>,
+    /// IANA timezone, e.g. `"America/Chicago"`.
+    pub timezone: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CityCandidate<'a> {
+    pub city: &'a City,
+    /// Higher is better. Each matching country or admin1 hint adds 1, a matching
+    /// timezone 0.5 and being close to `near` up to 1 (0.5 at 100 km). Candidates with
+    /// the same score are ordered by population, largest first, then geonameid.
+    pub score: f64,
+}
+
+/// The city closest to a point, returned by
+/// [`crate::Geonamescache::nearest_city_with_distance`]. Serialises as the city with
+/// an extra `distance_km` field.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct NearestCity<'a> {
+    #[serde(flatten)]
+    pub city: &'a City,
+    /// Great-circle distance from the point.
+    pub distance_km: f64,
+}
+
+const COUNTRY_WEIGHT: f64 = 1.0;
+const ADMIN1_WEIGHT: f64 = 1.0;
+const TIMEZONE_WEIGHT: f64 = 0.5;
+const NEAR_WEIGHT: f64 = 1.0;
+/// Distance in km at which half of [`NEAR_WEIGHT`] is given.
+const NEAR_HALF_SCORE_KM: f64 = 100.0;
+
+pub(crate) fn disambiguate_city<'a>(
+    gc: &'a Geonamescache,
+    name: &str,
+    hints: &CityHints,
+) -> Vec<CityCandidate<'a>> {
+    let country = hints.country.as_deref().and_then(|c| gc.resolve_country(c));
+    let admin1 = hints.admin1.as_deref().map(|a| Qualifier::region(gc, a));
+
+    let score = |city: &City| {
+        let mut score = 0.0;
+        if country.is_some_and(|c| c.iso == city.countrycode) {
+            score += COUNTRY_WEIGHT;
+        }
+        if admin1.as_ref().is_some_and(|a| a.matches(city)) {
+            score += ADMIN1_WEIGHT;
+        }
+        if hints.timezone.as_deref() == Some(city.timezone.as_str()) {
+            score += TIMEZONE_WEIGHT;
+        }
+        if let Some(p) = hints.near {
+            let d = haversine_km(p.lat, p.lon, city.latitude, city.longitude);
+            score += NEAR_WEIGHT * NEAR_HALF_SCORE_KM / (NEAR_HALF_SCORE_KM + d);
+        }
+        score
+    };
+
+    let mut candidates: Vec<CityCandidate> = gc
+        .city_ids_by_name(name)
+        .iter()
+        .map(|gid| &gc.get_cities()[gid])
+        .map(|city| CityCandidate {
+            city,
+            score: score(city),
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.city.population.cmp(&a.city.population))
+            .then(a.city.geonameid.cmp(&b.city.geonameid))
+    });
+    candidates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn city(countrycode: &str, admin1code: &str) -> City {
+        City {
+            countrycode: countrycode.into(),
+            admin1code: admin1code.into(),
+            ..City::default()
+        }
+    }
+
+    #[test]
+    fn test_state_before_country() {
+        let gc = Geonamescache::global();
+        let test_data = [
+            ("IL", city("US", "IL"), city("IL", "")),
+            ("CA", city("US", "CA"), city("CA", "08")),
+            ("Georgia", city("US", "GA"), city("GE", "")),
+        ];
+        for (text, in_state, in_country) in test_data {
+            let qualifiers = [Qualifier::new(gc, text)];
+            assert!(qualifiers[0].matches(&in_state), "{}", text);
+            assert!(qualifiers[0].matches(&in_country), "{}", text);
+            assert!(
+                rank(&qualifiers, &in_state) > rank(&qualifiers, &in_country),
+                "{}",
+                text
+            );
+            assert!(rank(&qualifiers, &in_country) > rank(&qualifiers, &city("FR", "")));
+        }
+
+        // A second qualifier matching the country outweighs the state reading.
+        let qualifiers = [Qualifier::new(gc, "CA"), Qualifier::new(gc, "Canada")];
+        assert!(rank(&qualifiers, &city("CA", "08")) > rank(&qualifiers, &city("US", "CA")));
+    }
+}
@@ -1,21 +1,241 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
 
-use crate::data::COUNTRY_NAMES;
-use crate::geotypes::Country;
+use crate::aliases::CountryAliases;
+use crate::geotypes::{City, Continent, Country, UsCounty, UsState};
 use crate::Geonamescache;
 
-pub struct CountryMapper {
-    dataset: HashMap<String, String>,
+/// Case-insensitive map from a string key to any value, built from one of the
+/// datasets of a [`Geonamescache`] with [`city`], [`continent`], [`country_with`],
+/// [`us_state`] or [`us_county`].
+///
+/// Several records may share a key (e.g. countries by currency code). No value is
+/// dropped: [`Mapper::get_all`] returns all of them, and the shared keys are found
+/// when the mapper is built and listed by [`Mapper::collisions`].
+pub struct Mapper<'a, V> {
+    dataset: HashMap<Cow<'a, str>, Entry<'a, V>>,
+    /// Lowercase keys shared by more than one record, sorted.
+    collisions: Vec<Cow<'a, str>>,
+    /// Alternative spellings, for mappers whose keys are country names.
+    country_aliases: Option<Arc<CountryAliases>>,
+    /// The same, for mappers whose values are country names, used by
+    /// [`Mapper::invert`].
+    value_aliases: Option<Arc<CountryAliases>>,
 }
 
-impl CountryMapper {
-    pub fn get(&self, key: &str) -> Option<&str> {
+/// Values sharing a lowercase key, with the keys as they were given.
+struct Entry<'a, V> {
+    keys: Vec<Cow<'a, str>>,
+    values: Vec<V>,
+}
+
+impl<'a, V> Mapper<'a, V> {
+    fn new<I, K>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Cow<'a, str>>,
+    {
+        let mut dataset: HashMap<_, Entry<V>> = HashMap::new();
+        for (k, v) in pairs {
+            let k = k.into();
+            let entry = dataset.entry(to_lowercase(k.clone())).or_insert(Entry {
+                keys: Vec::new(),
+                values: Vec::new(),
+            });
+            entry.keys.push(k);
+            entry.values.push(v);
+        }
+        let mut collisions: Vec<_> = dataset
+            .iter()
+            .filter(|(_, e)| e.values.len() > 1)
+            .map(|(k, _)| k.clone())
+            .collect();
+        collisions.sort();
+        Self {
+            dataset,
+            collisions,
+            country_aliases: None,
+            value_aliases: None,
+        }
+    }
+
+    /// Build a mapper from the key and value of each country. Country aliases apply to
+    /// the keys if they are the country names, and to the values once inverted if
+    /// `value_is_name` holds for all of them.
+    fn from_countries<'c, I, K>(
+        triples: I,
+        aliases: Arc<CountryAliases>,
+        value_is_name: impl Fn(&V, &str) -> bool,
+    ) -> Self
+    where
+        I: IntoIterator<Item = (&'c Country, K, V)>,
+        K: Into<Cow<'a, str>>,
+    {
+        let (mut keys_are_names, mut values_are_names) = (true, true);
+        let pairs = triples.into_iter().map(|(country, k, v)| {
+            let k = k.into();
+            keys_are_names &= k == country.name;
+            values_are_names &= value_is_name(&v, &country.name);
+            (k, v)
+        });
+        let mut mapper = Self::new(pairs);
+        mapper.country_aliases = keys_are_names.then(|| Arc::clone(&aliases));
+        mapper.value_aliases = values_are_names.then_some(aliases);
+        mapper
+    }
+
+    fn entry(&self, key: &str) -> Option<&Entry<'a, V>> {
         let key = key.to_lowercase();
-        let key = COUNTRY_NAMES
-            .get(key.as_str())
-            .unwrap_or(&key.as_str())
-            .to_string();
-        self.dataset.get(&key).map(|x| x.as_str())
+        self.dataset.get(key.as_str()).or_else(|| {
+            let name = self.country_aliases.as_ref()?.resolve(&key)?;
+            self.dataset.get(name.as_str())
+        })
+    }
+
+    /// Get the value for `key`. If several records share the key, which one is
+    /// returned is unspecified; use [`Mapper::get_all`] to get all of them.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.get_all(key).first()
+    }
+
+    /// Get all the values for `key`.
+    pub fn get_all(&self, key: &str) -> &[V] {
+        self.entry(key).map_or(&[], |e| e.values.as_slice())
+    }
+
+    /// Keys shared by more than one record, with all their values, in key order.
+    pub fn collisions(&self) -> impl Iterator<Item = (&str, &[V])> {
+        self.collisions.iter().map(|k| {
+            let e = &self.dataset[k];
+            (e.keys[0].as_ref(), e.values.as_slice())
+        })
+    }
+
+    /// Fail if any key is shared by more than one record, instead of keeping all of
+    /// them.
+    pub fn ensure_unique(self) -> Result<Self, KeyCollisions> {
+        if self.collisions.is_empty() {
+            return Ok(self);
+        }
+        let keys = self.collisions().map(|(k, _)| k.to_string()).collect();
+        Err(KeyCollisions { keys })
+    }
+
+    /// Swap keys and values, e.g. to turn a name to ISO3 map into an ISO3 to name map.
+    /// Values shared by several keys map to all of them. Country aliases follow the
+    /// country names: they apply to the new keys only if the values were names.
+    pub fn invert(&self) -> Mapper<'a, Cow<'a, str>>
+    where
+        V: Clone + Into<Cow<'a, str>>,
+    {
+        let pairs = self.dataset.values().flat_map(|e| {
+            e.values
+                .iter()
+                .zip(&e.keys)
+                .map(|(v, k)| (v.clone().into(), k.clone()))
+        });
+        let mut mapper = Mapper::new(pairs);
+        mapper.country_aliases = self.value_aliases.clone();
+        mapper.value_aliases = self.country_aliases.clone();
+        mapper
+    }
+}
+
+/// Lowercase a key, only allocating if it isn't lowercase already.
+fn to_lowercase(key: Cow<str>) -> Cow<str> {
+    if key.chars().any(char::is_uppercase) {
+        Cow::Owned(key.to_lowercase())
+    } else {
+        key
+    }
+}
+
+/// Keys shared by more than one record, returned by [`Mapper::ensure_unique`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyCollisions {
+    pub keys: Vec<String>,
+}
+
+impl fmt::Display for KeyCollisions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "keys shared by several records: {}",
+            self.keys.join(", ")
+        )
+    }
+}
+
+impl Error for KeyCollisions {}
+
+/// A country field name that isn't one of [`Country::FIELDS`], returned by
+/// [`Geonamescache::country_field_mapper`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownField {
+    pub field: String,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown country field {:?}, expected one of {}",
+            self.field,
+            Country::FIELDS.join(", ")
+        )
+    }
+}
+
+impl Error for UnknownField {}
+
+/// Map between two fields in Country. It can own its data (see [`country`]) or
+/// borrow it from a [`Geonamescache`] (see [`Geonamescache::country_mapper`]).
+pub struct CountryMapper<'a> {
+    mapper: Mapper<'a, Cow<'a, str>>,
+}
+
+impl<'a> CountryMapper<'a> {
+    /// Map the key of each country to its value.
+    pub(crate) fn new<'c, I, K, V>(triples: I, aliases: Arc<CountryAliases>) -> Self
+    where
+        I: IntoIterator<Item = (&'c Country, K, V)>,
+        K: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        let triples = triples.into_iter().map(|(c, k, v)| (c, k, v.into()));
+        Self {
+            mapper: Mapper::from_countries(triples, aliases, |v, name| v == name),
+        }
+    }
+
+    /// Get the value for `key`. If several countries share the key, which one is
+    /// returned is unspecified; use [`CountryMapper::get_all`] to get all of them.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.mapper.get(key).map(|x| x.as_ref())
+    }
+
+    pub fn get_all(&self, key: &str) -> impl Iterator<Item = &str> {
+        self.mapper.get_all(key).iter().map(|x| x.as_ref())
+    }
+
+    /// Keys shared by more than one country, in key order.
+    pub fn collisions(&self) -> impl Iterator<Item = &str> {
+        self.mapper.collisions().map(|(k, _)| k)
+    }
+
+    pub fn ensure_unique(self) -> Result<Self, KeyCollisions> {
+        let mapper = self.mapper.ensure_unique()?;
+        Ok(Self { mapper })
+    }
+
+    /// Swap keys and values, e.g. to turn a name to ISO3 map into an ISO3 to name map.
+    pub fn invert(&self) -> CountryMapper<'a> {
+        CountryMapper {
+            mapper: self.mapper.invert(),
+        }
     }
 }
 
@@ -25,6 +245,9 @@ impl CountryMapper {
 /// This map will be case-insensitive - the capitalisation of the key won't change
 /// the result.
 ///
+/// The countries come from [`Geonamescache::global`], and are cloned for the closure.
+/// [`Geonamescache::country_mapper`] borrows from the cache instead.
+///
 /// # Examples
 /// ```
 /// use geonamescache::mappers::country;
@@ -32,23 +255,85 @@ impl CountryMapper {
 /// let uk = name_to_iso3.get("united kingdom");
 /// assert_eq!(uk, Some("GBR"));
 /// ```
-pub fn country<F>(f: F) -> CountryMapper
+pub fn country<F>(f: F) -> CountryMapper<'static>
 where
     F: Fn(Country) -> (String, String),
 {
-    let mut d: HashMap<_, _> = Geonamescache::new()
-        .get_countries()
-        .values()
-        .cloned()
-        .map(f)
-        .collect();
-    let id: HashMap<_, _> = d
-        .iter()
-        .map(|(k, v)| (k.to_lowercase(), v.clone()))
-        .collect();
-    d.extend(id);
+    let gc = Geonamescache::global();
+    let triples = gc.get_countries().values().map(|c| {
+        let (k, v) = f(c.clone());
+        (c, k, v)
+    });
+    CountryMapper::new(triples, gc.country_aliases())
+}
+
+/// Create a map from Country to any value. Unlike [`country`] and
+/// [`Geonamescache::country_mapper`], the value isn't restricted to strings.
+///
+/// Keys are case-insensitive and may use any of the alternative country names the
+/// other country mappers accept.
+///
+/// # Examples
+/// ```
+/// use geonamescache::{mappers, Geonamescache};
+/// let gc = Geonamescache::new();
+/// let by_name = mappers::country_with(&gc, |c| (&c.name, c));
+/// assert_eq!(by_name.get("Burma").map(|c| c.iso.as_str()), Some("MM"));
+/// ```
+pub fn country_with<'a, F, K, V>(gc: &'a Geonamescache, f: F) -> Mapper<'a, V>
+where
+    F: Fn(&'a Country) -> (K, V),
+    K: Into<Cow<'a, str>>,
+{
+    let triples = gc.get_countries().values().map(|c| {
+        let (k, v) = f(c);
+        (c, k, v)
+    });
+    Mapper::from_countries(triples, gc.country_aliases(), |_, _| false)
+}
+
+/// Create a case-insensitive map from City to any value.
+///
+/// # Examples
+/// ```
+/// use geonamescache::{mappers, Geonamescache};
+/// let gc = Geonamescache::new();
+/// let population = mappers::city(&gc, |c| (&c.name, c.population));
+/// assert!(population.get("madrid").is_some());
+/// ```
+pub fn city<'a, F, K, V>(gc: &'a Geonamescache, f: F) -> Mapper<'a, V>
+where
+    F: Fn(&'a City) -> (K, V),
+    K: Into<Cow<'a, str>>,
+{
+    Mapper::new(gc.get_cities().values().map(f))
+}
 
-    CountryMapper { dataset: d }
+/// Create a case-insensitive map from Continent to any value.
+pub fn continent<'a, F, K, V>(gc: &'a Geonamescache, f: F) -> Mapper<'a, V>
+where
+    F: Fn(&'a Continent) -> (K, V),
+    K: Into<Cow<'a, str>>,
+{
+    Mapper::new(gc.get_continents().values().map(f))
+}
+
+/// Create a case-insensitive map from UsState to any value.
+pub fn us_state<'a, F, K, V>(gc: &'a Geonamescache, f: F) -> Mapper<'a, V>
+where
+    F: Fn(&'a UsState) -> (K, V),
+    K: Into<Cow<'a, str>>,
+{
+    Mapper::new(gc.get_us_states().values().map(f))
+}
+
+/// Create a case-insensitive map from UsCounty to any value.
+pub fn us_county<'a, F, K, V>(gc: &'a Geonamescache, f: F) -> Mapper<'a, V>
+where
+    F: Fn(&'a UsCounty) -> (K, V),
+    K: Into<Cow<'a, str>>,
+{
+    Mapper::new(gc.get_us_counties().iter().map(f))
 }
 
 #[cfg(test)]
@@ -86,4 +371,156 @@ mod test {
         assert_eq!(map("The Netherlands"), "NLD");
         assert_eq!(map("USA"), "USA");
     }
+
+    #[test]
+    fn test_borrowed_country_mapper() {
+        let gc = Geonamescache::new();
+        let name_to_iso3 = gc.country_mapper(|c| (&c.name, &c.iso3));
+        let iso3_to_name = gc.country_mapper(|c| (&c.iso3, &c.name));
+
+        assert_eq!(name_to_iso3.get("Burma"), Some("MMR"));
+        assert_eq!(name_to_iso3.get("united kingdom"), Some("GBR"));
+        assert_eq!(iso3_to_name.get("fra"), Some("France"));
+        assert_eq!(iso3_to_name.get("XXX"), None);
+    }
+
+    #[test]
+    fn test_country_with_mapper() {
+        let gc = Geonamescache::new();
+        let population = country_with(&gc, |c| (&c.iso3, c.population));
+        let by_name = country_with(&gc, |c| (&c.name, c));
+
+        assert_eq!(
+            population.get("esp"),
+            Some(&gc.get_countries()["ES"].population)
+        );
+        assert_eq!(by_name.get("The Netherlands").unwrap().iso, "NL");
+        assert_eq!(by_name.get("Korea, South").unwrap().iso, "KR");
+        assert!(by_name.get("Atlantis").is_none());
+    }
+
+    #[test]
+    fn test_city_mapper() {
+        let gc = Geonamescache::new();
+        let by_id = city(&gc, |c| (c.geonameid.to_string(), c));
+
+        assert_eq!(by_id.get("3191316").unwrap().name, "Samobor");
+    }
+
+    #[test]
+    fn test_continent_mapper() {
+        let gc = Geonamescache::new();
+        let name_to_code = continent(&gc, |c| (&c.name, c.continent_code.as_str()));
+
+        assert_eq!(name_to_code.get("south america"), Some(&"SA"));
+        assert_eq!(name_to_code.get("EUROPE"), Some(&"EU"));
+    }
+
+    #[test]
+    fn test_us_state_mapper() {
+        let gc = Geonamescache::new();
+        let name_to_fips = us_state(&gc, |s| (&s.name, &s.fips));
+
+        assert_eq!(
+            name_to_fips.get("new mexico").map(|x| x.as_str()),
+            Some("35")
+        );
+    }
+
+    #[test]
+    fn test_us_county_mapper() {
+        let gc = Geonamescache::new();
+        let fips_to_state = us_county(&gc, |c| (&c.fips, &c.state));
+
+        assert_eq!(fips_to_state.get("01003").map(|x| x.as_str()), Some("AL"));
+    }
+
+    #[test]
+    fn test_aliases_do_not_shadow_keys() {
+        let gc = Geonamescache::new();
+        let iso3_to_name = gc.country_mapper(|c| (&c.iso3, &c.name));
+
+        // "usa" is also an alias of "United States".
+        assert_eq!(iso3_to_name.get("USA"), Some("United States"));
+    }
+
+    #[test]
+    fn test_registered_aliases() {
+        let gc = Geonamescache::new();
+        let name_to_iso = gc.country_mapper(|c| (&c.name, &c.iso));
+        let population = country_with(&gc, |c| (&c.name, c.population));
+        assert_eq!(name_to_iso.get("Türkiye"), None);
+
+        gc.add_country_alias("Türkiye", "Turkey");
+        gc.add_country_alias("U.S.A.", "USA");
+
+        assert_eq!(name_to_iso.get("türkiye"), Some("TR"));
+        assert_eq!(name_to_iso.get("U.S.A."), Some("US"));
+        assert!(population.get("TÜRKIYE").is_some());
+        assert_eq!(
+            gc.country_mapper(|c| (&c.name, &c.iso)).get("Türkiye"),
+            Some("TR")
+        );
+    }
+
+    #[test]
+    fn test_one_to_many() {
+        let gc = Geonamescache::new();
+        let by_currency = country_with(&gc, |c| (&c.currencycode, c.iso.as_str()));
+
+        let euro = by_currency.get_all("eur");
+        assert!(euro.contains(&"FR"));
+        assert!(euro.contains(&"ES"));
+        assert!(by_currency.get("EUR").is_some());
+        assert!(by_currency.get_all("XXX").is_empty());
+    }
+
+    #[test]
+    fn test_collisions() {
+        let gc = Geonamescache::new();
+        let by_currency = country_with(&gc, |c| (&c.currencycode, c.iso.as_str()));
+        let by_iso = country_with(&gc, |c| (&c.iso, c.iso.as_str()));
+
+        assert!(by_currency
+            .collisions()
+            .any(|(k, v)| k == "EUR" && v.len() > 1));
+        assert_eq!(by_iso.collisions().count(), 0);
+
+        let err = by_currency.ensure_unique().err().unwrap();
+        assert!(err.keys.contains(&"EUR".to_string()));
+        assert!(by_iso.ensure_unique().is_ok());
+
+        let currency_to_iso = gc.country_mapper(|c| (&c.currencycode, &c.iso));
+        assert!(currency_to_iso.collisions().any(|k| k == "USD"));
+        assert!(currency_to_iso.ensure_unique().is_err());
+
+        // Found when the mapper is built, in a stable order.
+        let continent_to_iso = gc.country_field_mapper("continentcode", "iso").unwrap();
+        let keys: Vec<&str> = continent_to_iso.collisions().collect();
+        assert_eq!(keys, ["AF", "AN", "AS", "EU", "NA", "OC", "SA"]);
+    }
+
+    #[test]
+    fn test_invert() {
+        let gc = Geonamescache::new();
+        let name_to_iso3 = gc.country_mapper(|c| (&c.name, &c.iso3));
+        let iso3_to_name = name_to_iso3.invert();
+
+        assert_eq!(iso3_to_name.get("nld"), Some("Netherlands"));
+        assert_eq!(iso3_to_name.get("KOR"), Some("South Korea"));
+
+        // Aliases of country names follow the names to the other side.
+        assert!(name_to_iso3.mapper.country_aliases.is_some());
+        assert!(iso3_to_name.mapper.country_aliases.is_none());
+        let name_to_iso3 = iso3_to_name.invert();
+        assert_eq!(name_to_iso3.get("Burma"), Some("MMR"));
+        let iso3_to_name = gc.country_mapper(|c| (&c.iso3, &c.name));
+        assert_eq!(iso3_to_name.invert().get("Burma"), Some("MMR"));
+
+        let iso_to_currency = country_with(&gc, |c| (&c.iso, &c.currencycode));
+        let currency_to_iso = iso_to_currency.invert();
+        let euro = currency_to_iso.get_all("EUR");
+        assert!(euro.contains(&"FR".into()));
+        assert!(euro.contains(&"DE".into()));
+    }
 }
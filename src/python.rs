@@ -0,0 +1,388 @@
+//! Python bindings, enabled with the `python` feature and built with maturin from
+//! `bindings/python`.
+//!
+//! The module mirrors the API of the original Python package: records are plain
+//! dicts, and each dataset is converted once and then returned as the same dict on
+//! every call.
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::sync::PyOnceLock;
+use pyo3::types::{PyBool, PyDict, PyList, PyString};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::mappers::CountryMapper;
+use crate::{City, Geonamescache};
+
+/// Population threshold of the bundled cities, those of the GeoNames `cities15000`
+/// dump.
+const DATASET_MIN_POPULATION: u32 = 15000;
+
+/// Convert a record to Python through its JSON representation.
+fn to_py<'py, T: Serialize + ?Sized>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let value = serde_json::to_value(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    json_to_py(py, &value)
+}
+
+fn json_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Null => py.None().into_bound(py),
+        Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => i.into_pyobject(py)?.into_any(),
+            (None, Some(f)) => f.into_pyobject(py)?.into_any(),
+            (None, None) => n.to_string().into_pyobject(py)?.into_any(),
+        },
+        Value::String(s) => PyString::new(py, s).into_any(),
+        Value::Array(values) => {
+            let items = values
+                .iter()
+                .map(|v| json_to_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+/// `{record[key]: record}` for every record in the dataset.
+fn by_key<'py>(dataset: &Bound<'py, PyDict>, key: &str) -> PyResult<Bound<'py, PyDict>> {
+    let result = PyDict::new(dataset.py());
+    for record in dataset.values() {
+        result.set_item(record.get_item(key)?, record)?;
+    }
+    Ok(result)
+}
+
+fn matches(value: &str, query: &str, case_sensitive: bool, contains_search: bool) -> bool {
+    let value = if case_sensitive {
+        value.to_string()
+    } else {
+        value.to_lowercase()
+    };
+    if contains_search {
+        value.contains(query)
+    } else {
+        value == query
+    }
+}
+
+#[pyclass(name = "GeonamesCache", module = "geonamescache", frozen)]
+pub struct PyGeonamescache {
+    gc: Geonamescache,
+    min_city_population: u32,
+    continents: PyOnceLock<Py<PyDict>>,
+    countries: PyOnceLock<Py<PyDict>>,
+    us_states: PyOnceLock<Py<PyDict>>,
+    cities: PyOnceLock<Py<PyDict>>,
+    us_counties: PyOnceLock<Py<PyList>>,
+}
+
+impl PyGeonamescache {
+    fn cities(&self) -> impl Iterator<Item = &City> {
+        self.gc
+            .get_cities()
+            .values()
+            .filter(move |c| c.population >= self.min_city_population)
+    }
+
+    /// The cached dicts of the matching cities, ordered by geonameid.
+    fn find_cities<'py, P>(&self, py: Python<'py>, predicate: P) -> PyResult<Bound<'py, PyList>>
+    where
+        P: Fn(&City) -> bool,
+    {
+        let mut cities: Vec<&City> = self.cities().filter(|c| predicate(c)).collect();
+        cities.sort_by_key(|c| c.geonameid);
+        let items = cities
+            .into_iter()
+            .map(|c| self.city(py, c))
+            .collect::<PyResult<Vec<_>>>()?;
+        PyList::new(py, items)
+    }
+
+    /// The cached dict of a city, so that every method returns the same objects.
+    fn city<'py>(&self, py: Python<'py>, city: &City) -> PyResult<Bound<'py, PyAny>> {
+        self.get_cities(py)?
+            .as_any()
+            .get_item(city.geonameid.to_string())
+    }
+}
+
+#[pymethods]
+impl PyGeonamescache {
+    /// Only the bundled cities are available, so a `min_city_population` below that
+    /// of the dataset the crate was built with raises `ValueError`.
+    #[new]
+    #[pyo3(signature = (min_city_population = DATASET_MIN_POPULATION))]
+    fn new(min_city_population: u32) -> PyResult<Self> {
+        if min_city_population < DATASET_MIN_POPULATION {
+            return Err(PyValueError::new_err(format!(
+                "min_city_population must be at least {}, the population threshold of \
+                 the bundled cities",
+                DATASET_MIN_POPULATION
+            )));
+        }
+        Ok(PyGeonamescache {
+            gc: Geonamescache::new(),
+            min_city_population,
+            continents: PyOnceLock::new(),
+            countries: PyOnceLock::new(),
+            us_states: PyOnceLock::new(),
+            cities: PyOnceLock::new(),
+            us_counties: PyOnceLock::new(),
+        })
+    }
+
+    fn get_dataset_by_key<'py>(
+        &self,
+        dataset: &Bound<'py, PyDict>,
+        key: &str,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        by_key(dataset, key)
+    }
+
+    fn get_continents<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = self.continents.get_or_try_init(py, || {
+            to_py(py, self.gc.get_continents())?
+                .cast_into::<PyDict>()
+                .map(Bound::unbind)
+                .map_err(PyErr::from)
+        })?;
+        Ok(dict.bind(py).clone())
+    }
+
+    fn get_countries<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = self.countries.get_or_try_init(py, || {
+            to_py(py, self.gc.get_countries())?
+                .cast_into::<PyDict>()
+                .map(Bound::unbind)
+                .map_err(PyErr::from)
+        })?;
+        Ok(dict.bind(py).clone())
+    }
+
+    fn get_us_states<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = self.us_states.get_or_try_init(py, || {
+            to_py(py, self.gc.get_us_states())?
+                .cast_into::<PyDict>()
+                .map(Bound::unbind)
+                .map_err(PyErr::from)
+        })?;
+        Ok(dict.bind(py).clone())
+    }
+
+    fn get_countries_by_names<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        by_key(&self.get_countries(py)?, "name")
+    }
+
+    fn get_us_states_by_names<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        by_key(&self.get_us_states(py)?, "name")
+    }
+
+    fn get_cities<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = self.cities.get_or_try_init(py, || -> PyResult<_> {
+            let dict = PyDict::new(py);
+            for city in self.cities() {
+                dict.set_item(city.geonameid.to_string(), to_py(py, city)?)?;
+            }
+            Ok(dict.unbind())
+        })?;
+        Ok(dict.bind(py).clone())
+    }
+
+    /// A list of `{geonameid: city}` dicts, one per city with that name.
+    fn get_cities_by_name<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyList>> {
+        let mut cities: Vec<&City> = self.cities().filter(|c| c.name == name).collect();
+        cities.sort_by_key(|c| c.geonameid);
+
+        let items = cities
+            .into_iter()
+            .map(|c| {
+                let item = PyDict::new(py);
+                item.set_item(c.geonameid.to_string(), self.city(py, c)?)?;
+                Ok(item)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        PyList::new(py, items)
+    }
+
+    fn get_us_counties<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let list = self.us_counties.get_or_try_init(py, || {
+            to_py(py, self.gc.get_us_counties())?
+                .cast_into::<PyList>()
+                .map(Bound::unbind)
+                .map_err(PyErr::from)
+        })?;
+        Ok(list.bind(py).clone())
+    }
+
+    /// Cities whose `attribute` matches the query. `attribute` is one of the city
+    /// fields holding text, e.g. `"name"`, or `"alternatenames"`, which matches if
+    /// any of the names does.
+    #[pyo3(signature = (query, attribute = "alternatenames", case_sensitive = false, contains_search = true))]
+    fn search_cities<'py>(
+        &self,
+        py: Python<'py>,
+        query: &str,
+        attribute: &str,
+        case_sensitive: bool,
+        contains_search: bool,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let query = if case_sensitive {
+            query.to_string()
+        } else {
+            query.to_lowercase()
+        };
+        let is_match = |value: &str| matches(value, &query, case_sensitive, contains_search);
+        let field: fn(&City) -> &str = match attribute {
+            "alternatenames" => {
+                return self.find_cities(py, |c| c.alternatenames.iter().any(|n| is_match(n)))
+            }
+            "name" => |c| &c.name,
+            "countrycode" => |c| &c.countrycode,
+            "timezone" => |c| &c.timezone,
+            "admin1code" => |c| &c.admin1code,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "cannot search cities by {:?}",
+                    attribute
+                )))
+            }
+        };
+        self.find_cities(py, |c| is_match(field(c)))
+    }
+}
+
+/// A function mapping one country field to another, returned by `mappers.country`.
+#[pyclass(name = "CountryMapper", module = "geonamescache.mappers", frozen)]
+pub struct PyCountryMapper {
+    mapper: CountryMapper<'static>,
+}
+
+#[pymethods]
+impl PyCountryMapper {
+    fn __call__(&self, key: &str) -> Option<&str> {
+        self.mapper.get(key)
+    }
+}
+
+/// Create a function that maps the `from_key` field of a country to its `to_key`
+/// field, e.g. `country(from_key="name", to_key="iso3")("Spain") == "ESP"`. Returns
+/// `None` for unknown values.
+#[pyfunction]
+#[pyo3(signature = (from_key = "name", to_key = "iso"))]
+fn country(from_key: &str, to_key: &str) -> PyResult<PyCountryMapper> {
+    let mapper = Geonamescache::global()
+        .country_field_mapper(from_key, to_key)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(PyCountryMapper { mapper })
+}
+
+#[pymodule]
+fn geonamescache(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_class::<PyGeonamescache>()?;
+
+    let mappers = PyModule::new(py, "mappers")?;
+    mappers.add_class::<PyCountryMapper>()?;
+    mappers.add_function(wrap_pyfunction!(country, &mappers)?)?;
+    m.add_submodule(&mappers)?;
+    // Make `import geonamescache.mappers` work, not only attribute access.
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item("geonamescache.mappers", &mappers)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    /// Run Python code with the module importable as `geonamescache`.
+    fn run(code: &CStr) {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = pyo3::wrap_pymodule!(geonamescache)(py);
+            py.import("sys")?
+                .getattr("modules")?
+                .set_item("geonamescache", module)?;
+            py.run(code, None, None)
+        })
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    #[test]
+    fn test_datasets() {
+        run(c"
+import geonamescache
+gc = geonamescache.GeonamesCache()
+assert gc.get_countries()['FR']['iso3'] == 'FRA'
+assert gc.get_countries() is gc.get_countries()
+assert gc.get_us_states()['IL']['name'] == 'Illinois'
+assert gc.get_countries_by_names()['Spain']['iso'] == 'ES'
+");
+    }
+
+    #[test]
+    fn test_cities() {
+        run(c"
+import geonamescache
+gc = geonamescache.GeonamesCache()
+madrids = gc.get_cities_by_name('Madrid')
+assert isinstance(madrids, list)
+assert [list(m) for m in madrids] == [['3117735'], ['3675707']]
+assert madrids[0]['3117735'] is gc.get_cities()['3117735']
+assert gc.get_cities_by_name('madrid') == []
+
+# By default, a case-insensitive substring of any alternate name.
+kiev = gc.search_cities('KIEV')
+assert 703448 in [c['geonameid'] for c in kiev]
+assert gc.search_cities('Kiev', attribute='name', contains_search=False) == []
+assert gc.search_cities('kiev', case_sensitive=True) == []
+try:
+    gc.search_cities('x', attribute='population')
+    raise AssertionError('no ValueError')
+except ValueError:
+    pass
+");
+    }
+
+    #[test]
+    fn test_min_city_population() {
+        run(c"
+import geonamescache
+gc = geonamescache.GeonamesCache(min_city_population=1000000)
+assert all(c['population'] >= 1000000 for c in gc.get_cities().values())
+for population in (500, 14999):
+    try:
+        geonamescache.GeonamesCache(min_city_population=population)
+        raise AssertionError('no ValueError')
+    except ValueError:
+        pass
+");
+    }
+
+    #[test]
+    fn test_country_mapper() {
+        run(c"
+from geonamescache import mappers
+mapper = mappers.country(from_key='name', to_key='iso3')
+assert mapper('Spain') == 'ESP'
+assert mapper('Atlantis') is None
+assert mappers.country()('Germany') == 'DE'
+try:
+    mappers.country(to_key='nope')
+    raise AssertionError('no ValueError')
+except ValueError:
+    pass
+");
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use crate::aliases::CountryAliases;
+use crate::geotypes::Country;
+
+/// How a free-text value was matched to a country by
+/// [`crate::Geonamescache::resolve_country_match`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum MatchRule {
+    /// The name in the dataset, e.g. "Germany".
+    Name,
+    /// ISO 3166-1 alpha-2 code, e.g. "DE".
+    Iso,
+    /// ISO 3166-1 alpha-3 code, e.g. "DEU".
+    Iso3,
+    /// ISO 3166-1 numeric code, e.g. "276".
+    IsoNumeric,
+    /// An alternative spelling of the name, e.g. "Federal Republic of Germany".
+    Alias,
+    /// The name in another language, e.g. "Deutschland".
+    LocalizedName,
+    /// FIPS 10-4 code, e.g. "GM". Many of them are also the ISO code of a different
+    /// country, so they are only tried when nothing else matches.
+    Fips,
+}
+
+impl MatchRule {
+    /// How likely a match through this rule is to be the intended country, between 0
+    /// and 1.
+    pub fn confidence(self) -> f64 {
+        match self {
+            MatchRule::Name => 1.0,
+            MatchRule::Iso | MatchRule::Iso3 => 0.95,
+            MatchRule::IsoNumeric | MatchRule::Alias => 0.9,
+            MatchRule::LocalizedName => 0.8,
+            MatchRule::Fips => 0.5,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CountryMatch<'a> {
+    pub country: &'a Country,
+    pub rule: MatchRule,
+    pub confidence: f64,
+}
+
+/// Countries of a batch of free-text values, from
+/// [`crate::Geonamescache::resolve_countries`].
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct CountryBatch<'a> {
+    /// Country of each value, in the order of the input.
+    pub countries: Vec<Option<&'a Country>>,
+    /// Distinct values that couldn't be resolved, most frequent first.
+    pub unmatched: Vec<Unmatched>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Unmatched {
+    pub value: String,
+    /// Number of times the value appears in the batch.
+    pub count: usize,
+    /// Names of the countries with the closest spelling, best first.
+    pub suggestions: Vec<String>,
+}
+
+/// Maximum number of suggestions for an unmatched value.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Lookup tables from every identifier of a country to its ISO code.
+#[derive(Debug, Default)]
+pub(crate) struct CountryIndex {
+    iso3: HashMap<String, String>,
+    isonumeric: HashMap<u32, String>,
+    fips: HashMap<String, String>,
+    names: HashMap<String, String>,
+    localized_names: HashMap<String, String>,
+}
+
+impl CountryIndex {
+    pub fn new(countries: &HashMap<String, Country>) -> Self {
+        let mut index = Self::default();
+        for c in countries.values() {
+            index.iso3.insert(c.iso3.clone(), c.iso.clone());
+            // Kosovo has no numeric code and is stored as 0.
+            if c.isonumeric != 0 {
+                index.isonumeric.insert(c.isonumeric, c.iso.clone());
+            }
+            if !c.fips.is_empty() {
+                index.fips.insert(c.fips.clone(), c.iso.clone());
+            }
+            index.names.insert(c.name.to_lowercase(), c.iso.clone());
+            for n in &c.localized_names {
+                index
+                    .localized_names
+                    .entry(n.name.to_lowercase())
+                    .or_insert_with(|| c.iso.clone());
+            }
+        }
+        index
+    }
+
+    /// Find the country `query` refers to, trying the rules in the order of
+    /// [`MatchRule`].
+    pub fn resolve<'a>(
+        &self,
+        countries: &'a HashMap<String, Country>,
+        aliases: &CountryAliases,
+        query: &str,
+    ) -> Option<CountryMatch<'a>> {
+        let query = query.trim();
+        let upper = query.to_uppercase();
+        let lower = query.to_lowercase();
+
+        let (iso, rule) = None
+            .or_else(|| Some((self.names.get(&lower)?, MatchRule::Name)))
+            .or_else(|| Some((&countries.get(&upper)?.iso, MatchRule::Iso)))
+            .or_else(|| Some((self.iso3.get(&upper)?, MatchRule::Iso3)))
+            .or_else(|| {
+                let n = parse_isonumeric(query)?;
+                Some((self.isonumeric.get(&n)?, MatchRule::IsoNumeric))
+            })
+            .or_else(|| {
+                let name = aliases.resolve(&lower)?;
+                Some((self.names.get(&name)?, MatchRule::Alias))
+            })
+            .or_else(|| Some((self.localized_names.get(&lower)?, MatchRule::LocalizedName)))
+            .or_else(|| Some((self.fips.get(&upper)?, MatchRule::Fips)))?;
+
+        Some(CountryMatch {
+            country: countries.get(iso)?,
+            rule,
+            confidence: rule.confidence(),
+        })
+    }
+
+    /// Resolve every value, resolving each distinct value only once.
+    pub fn resolve_batch<'a, I, S>(
+        &self,
+        countries: &'a HashMap<String, Country>,
+        aliases: &CountryAliases,
+        values: I,
+    ) -> CountryBatch<'a>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut resolved: HashMap<String, Option<&'a Country>> = HashMap::new();
+        let mut unmatched: HashMap<String, usize> = HashMap::new();
+        let mut batch = CountryBatch::default();
+
+        for value in values {
+            let value = value.as_ref();
+            let country = match resolved.get(value) {
+                Some(&c) => c,
+                None => {
+                    let c = self.resolve(countries, aliases, value).map(|m| m.country);
+                    resolved.insert(value.to_string(), c);
+                    c
+                }
+            };
+            if country.is_none() {
+                *unmatched.entry(value.to_string()).or_default() += 1;
+            }
+            batch.countries.push(country);
+        }
+
+        let candidates = if unmatched.is_empty() {
+            Vec::new()
+        } else {
+            self.suggestion_candidates(aliases)
+        };
+        batch.unmatched = unmatched
+            .into_iter()
+            .map(|(value, count)| Unmatched {
+                suggestions: self.suggest(countries, &candidates, &value),
+                value,
+                count,
+            })
+            .collect();
+        batch
+            .unmatched
+            .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        batch
+    }
+
+    /// Every lowercase name and alias with the ISO code it stands for, the strings
+    /// `suggest` compares against. Built once per batch.
+    fn suggestion_candidates(&self, aliases: &CountryAliases) -> Vec<(String, &String)> {
+        let names = self.names.iter().map(|(n, iso)| (n.clone(), iso));
+        let aliases = aliases
+            .entries()
+            .into_iter()
+            .filter_map(|(alias, name)| Some((alias, self.names.get(&name)?)));
+        names.chain(aliases).collect()
+    }
+
+    /// Names of the countries whose name or alias is spelled most similarly to
+    /// `value`.
+    fn suggest(
+        &self,
+        countries: &HashMap<String, Country>,
+        candidates: &[(String, &String)],
+        value: &str,
+    ) -> Vec<String> {
+        let value = value.trim().to_lowercase();
+        let max_distance = (value.chars().count() / 3).max(2);
+
+        let mut best: HashMap<&String, usize> = HashMap::new();
+        for &(ref candidate, iso) in candidates {
+            let d = levenshtein(&value, candidate);
+            if d <= max_distance {
+                let e = best.entry(iso).or_insert(d);
+                *e = (*e).min(d);
+            }
+        }
+
+        let mut best: Vec<_> = best
+            .into_iter()
+            .map(|(iso, d)| (d, &countries[iso].name))
+            .collect();
+        best.sort();
+        best.into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+}
+
+/// An ISO 3166-1 numeric code: one to three digits, e.g. "040" or "40" for Austria.
+/// Unlike `str::parse`, signs are rejected.
+fn parse_isonumeric(query: &str) -> Option<u32> {
+    if !(1..=3).contains(&query.len()) || !query.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    query.parse().ok()
+}
+
+/// Edit distance between two strings, counted in characters.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_isonumeric() {
+        assert_eq!(parse_isonumeric("040"), Some(40));
+        assert_eq!(parse_isonumeric("276"), Some(276));
+        assert_eq!(parse_isonumeric("0"), Some(0));
+        assert_eq!(parse_isonumeric("+276"), None);
+        assert_eq!(parse_isonumeric("0276"), None);
+        assert_eq!(parse_isonumeric(""), None);
+        assert_eq!(parse_isonumeric("２７６"), None);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("germany", "germnay"), 2);
+        assert_eq!(levenshtein("côte", "cote"), 1);
+    }
+}
@@ -0,0 +1,202 @@
+//! Binary snapshot of the city dataset, the format [`crate::CompactCities`] reads in
+//! place, without deserialising.
+//!
+//! With the `snapshot` feature `build.rs` encodes `cities.json` at build time and
+//! `CompactCities::embedded` reads it straight from the binary. A snapshot written
+//! with [`write_snapshot`] can also be loaded from a file or a memory map and opened
+//! with [`CompactCities::from_bytes`](crate::CompactCities::from_bytes).
+use std::fmt;
+
+use crate::geotypes::City;
+
+pub(crate) mod write;
+
+use self::write::{Record, PREFERRED, SHORT, VERSION};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    InvalidUtf8,
+    /// A section holds values that don't fit the rest of the snapshot, e.g. a string
+    /// index past the end of the string table.
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => f.write_str("not a city snapshot"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(
+                    f,
+                    "unsupported snapshot version {} (expected {})",
+                    v, VERSION
+                )
+            }
+            SnapshotError::Truncated => f.write_str("snapshot is truncated"),
+            SnapshotError::InvalidUtf8 => f.write_str("snapshot strings are not UTF-8"),
+            SnapshotError::Corrupt(section) => write!(f, "corrupt snapshot: {}", section),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+pub(crate) fn record(c: &City) -> Record<'_> {
+    Record {
+        geonameid: c.geonameid,
+        name: &c.name,
+        latitude: c.latitude,
+        longitude: c.longitude,
+        countrycode: &c.countrycode,
+        population: c.population,
+        timezone: &c.timezone,
+        admin1code: &c.admin1code,
+        alternatenames: c.alternatenames.iter().map(String::as_str).collect(),
+        localized_names: c
+            .localized_names
+            .iter()
+            .map(|n| {
+                let preferred = if n.is_preferred_name { PREFERRED } else { 0 };
+                let short = if n.is_short_name { SHORT } else { 0 };
+                (n.name.as_str(), n.lang.as_str(), preferred | short)
+            })
+            .collect(),
+    }
+}
+
+/// Encode the cities in the snapshot format read by [`crate::CompactCities`].
+pub fn write_snapshot<'a, I>(cities: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = &'a City>,
+{
+    write::write(cities.into_iter().map(record))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::snapshot::write::HEADER_LEN;
+    use crate::{CompactCities, Geonamescache};
+
+    #[test]
+    fn test_roundtrip() {
+        let gc = Geonamescache::new();
+        let bytes = write_snapshot(gc.get_cities().values());
+        let snapshot = CompactCities::from_bytes(&bytes).unwrap();
+
+        assert_eq!(snapshot.len(), gc.get_cities().len());
+        for city in gc.get_cities().values() {
+            assert_eq!(&snapshot.get(city.geonameid).unwrap().to_city(), city);
+        }
+        assert!(snapshot.get(0).is_none());
+
+        let ids: Vec<u32> = snapshot.iter().map(|c| c.geonameid()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_by_name() {
+        let gc = Geonamescache::new();
+        let bytes = write_snapshot(gc.get_cities().values());
+        let snapshot = CompactCities::from_bytes(&bytes).unwrap();
+
+        let mut madrids: Vec<u32> = snapshot.by_name("MADRID").map(|c| c.geonameid()).collect();
+        madrids.sort_unstable();
+        assert_eq!(madrids, vec![3117735, 3675707]);
+        assert_eq!(snapshot.by_name("Atlantis").count(), 0);
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_embedded() {
+        let gc = Geonamescache::new();
+        let snapshot = CompactCities::embedded();
+        assert_eq!(snapshot.heap_size(), 0);
+        assert_eq!(snapshot.len(), gc.get_cities().len());
+        assert_eq!(
+            snapshot.get(3191316).unwrap().to_city(),
+            gc.get_cities()["3191316"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        assert_eq!(
+            CompactCities::from_bytes(b"GNCS").unwrap_err(),
+            SnapshotError::Truncated
+        );
+        assert_eq!(
+            CompactCities::from_bytes(&[0; HEADER_LEN]).unwrap_err(),
+            SnapshotError::BadMagic
+        );
+
+        let gc = Geonamescache::new();
+        let mut bytes = write_snapshot(gc.get_cities().values());
+        bytes.pop();
+        assert_eq!(
+            CompactCities::from_bytes(&bytes).unwrap_err(),
+            SnapshotError::Truncated
+        );
+        bytes[4] = 9;
+        assert_eq!(
+            CompactCities::from_bytes(&bytes).unwrap_err(),
+            SnapshotError::UnsupportedVersion(9)
+        );
+    }
+
+    /// Read every field of every city, which must not panic once a snapshot is open.
+    fn read_all(cities: &CompactCities) {
+        for city in cities.iter() {
+            city.to_city();
+            cities.by_name(city.name()).count();
+        }
+    }
+
+    #[test]
+    fn test_truncated_and_garbage() {
+        // A few cities, so that every byte can be corrupted in turn.
+        let gc = Geonamescache::new();
+        let cities = ["3191316", "2657896", "3448439"].map(|gid| &gc.get_cities()[gid]);
+        let bytes = write_snapshot(cities);
+        for len in 0..bytes.len() {
+            assert!(CompactCities::from_bytes(&bytes[..len]).is_err(), "{}", len);
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(
+            CompactCities::from_bytes(&longer).unwrap_err(),
+            SnapshotError::Corrupt("trailing bytes")
+        );
+
+        // A header claiming more entries than fit in memory.
+        let mut huge = bytes[..HEADER_LEN].to_vec();
+        huge[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            CompactCities::from_bytes(&huge).unwrap_err(),
+            SnapshotError::Truncated
+        );
+
+        // Every byte after the header overwritten in turn, and pseudo-random noise.
+        for i in HEADER_LEN..bytes.len() {
+            for value in [0x00, 0x7f, 0xc3, 0xff] {
+                let mut corrupt = bytes.clone();
+                corrupt[i] = value;
+                if let Ok(cities) = CompactCities::from_bytes(&corrupt) {
+                    read_all(&cities);
+                }
+            }
+        }
+        let mut state = 0x2545_f491_u32;
+        let mut noise = bytes.clone();
+        for byte in &mut noise[HEADER_LEN..] {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *byte = state as u8;
+        }
+        assert!(CompactCities::from_bytes(&noise).is_err());
+    }
+}
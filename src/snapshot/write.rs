@@ -0,0 +1,219 @@
+//! Writer of the city snapshot format. Shared with `build.rs`, so it only depends on
+//! `std`.
+//!
+//! All integers are little-endian `u32`s and coordinates `f64`s. After the header come the
+//! columns, each with one entry per city in geonameid order unless noted:
+//!
+//! | section                | entries                 |
+//! |------------------------|-------------------------|
+//! | header                 | see [`HEADER_LEN`]      |
+//! | geonameids             | cities                  |
+//! | names                  | cities                  |
+//! | latitudes              | cities                  |
+//! | longitudes             | cities                  |
+//! | countrycodes           | cities                  |
+//! | populations            | cities                  |
+//! | timezones              | cities                  |
+//! | admin1codes            | cities                  |
+//! | alternatename offsets  | cities + 1              |
+//! | alternatenames         | alternatenames          |
+//! | localized name offsets | cities + 1              |
+//! | localized names        | 3 × localized names     |
+//! | name keys              | cities                  |
+//! | rows sorted by name    | cities                  |
+//! | string starts          | strings + 1             |
+//! | string bytes           | bytes (UTF-8)           |
+//!
+//! String columns hold indices into the string table: string `i` is
+//! `bytes[starts[i]..starts[i + 1]]`. A localized name is its name, its language and
+//! its flags ([`PREFERRED`], [`SHORT`]). The name keys are the lowercase city names in
+//! ascending order, and the rows sorted by name the matching row of each key.
+use std::collections::HashMap;
+
+pub const MAGIC: &[u8; 4] = b"GNCS";
+pub const VERSION: u32 = 2;
+/// Magic, version, number of cities, alternate names, localized names, strings and
+/// string bytes.
+pub const HEADER_LEN: usize = 28;
+
+/// Flag of a localized name that is the preferred name in its language.
+pub const PREFERRED: u32 = 1;
+/// Flag of a localized name that is a short name.
+pub const SHORT: u32 = 2;
+
+/// A city to be written to a snapshot.
+pub struct Record<'a> {
+    pub geonameid: u32,
+    pub name: &'a str,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub countrycode: &'a str,
+    pub population: u32,
+    pub timezone: &'a str,
+    pub admin1code: &'a str,
+    pub alternatenames: Vec<&'a str>,
+    /// Name, language and flags of each localized name.
+    pub localized_names: Vec<(&'a str, &'a str, u32)>,
+}
+
+/// A record with its strings interned.
+struct Row {
+    geonameid: u32,
+    name: u32,
+    latitude: f64,
+    longitude: f64,
+    countrycode: u32,
+    population: u32,
+    timezone: u32,
+    admin1code: u32,
+    alternatenames: Vec<u32>,
+    localized_names: Vec<[u32; 3]>,
+}
+
+/// Encodes records one at a time, so that they don't have to be kept around.
+#[derive(Default)]
+pub struct Writer {
+    syms: HashMap<String, u32>,
+    starts: Vec<u32>,
+    bytes: Vec<u8>,
+    rows: Vec<Row>,
+}
+
+fn put_u32s<I: IntoIterator<Item = u32>>(out: &mut Vec<u8>, values: I) {
+    for v in values {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+fn put_f64s<I: IntoIterator<Item = f64>>(out: &mut Vec<u8>, values: I) {
+    for v in values {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+impl Writer {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&sym) = self.syms.get(s) {
+            return sym;
+        }
+        let sym = self.syms.len() as u32;
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+        self.bytes.extend_from_slice(s.as_bytes());
+        self.starts.push(self.bytes.len() as u32);
+        self.syms.insert(s.to_string(), sym);
+        sym
+    }
+
+    pub fn push(&mut self, r: Record) {
+        let row = Row {
+            geonameid: r.geonameid,
+            name: self.intern(r.name),
+            latitude: r.latitude,
+            longitude: r.longitude,
+            countrycode: self.intern(r.countrycode),
+            population: r.population,
+            timezone: self.intern(r.timezone),
+            admin1code: self.intern(r.admin1code),
+            alternatenames: r.alternatenames.iter().map(|n| self.intern(n)).collect(),
+            localized_names: r
+                .localized_names
+                .iter()
+                .map(|&(name, lang, flags)| [self.intern(name), self.intern(lang), flags])
+                .collect(),
+        };
+        self.rows.push(row);
+    }
+
+    fn string(&self, sym: u32) -> &str {
+        let range = self.starts[sym as usize] as usize..self.starts[sym as usize + 1] as usize;
+        std::str::from_utf8(&self.bytes[range]).unwrap()
+    }
+
+    /// Encode the records pushed so far as a snapshot.
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut rows = std::mem::take(&mut self.rows);
+        rows.sort_by_key(|r| r.geonameid);
+
+        let keys: Vec<u32> = rows
+            .iter()
+            .map(|r| {
+                let key = self.string(r.name).to_lowercase();
+                self.intern(&key)
+            })
+            .collect();
+        let mut by_name: Vec<u32> = (0..rows.len() as u32).collect();
+        by_name.sort_by(|&a, &b| {
+            self.string(keys[a as usize])
+                .cmp(self.string(keys[b as usize]))
+        });
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+
+        let alternatenames: Vec<u32> = rows.iter().flat_map(|r| r.alternatenames.clone()).collect();
+        let localized_names: Vec<u32> = rows
+            .iter()
+            .flat_map(|r| {
+                r.localized_names
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let offsets = |len: fn(&Row) -> usize| {
+            let mut offsets = vec![0u32];
+            let mut end = 0;
+            for r in &rows {
+                end += len(r) as u32;
+                offsets.push(end);
+            }
+            offsets
+        };
+
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put_u32s(
+            &mut out,
+            [
+                VERSION,
+                rows.len() as u32,
+                alternatenames.len() as u32,
+                (localized_names.len() / 3) as u32,
+                (self.starts.len() - 1) as u32,
+                self.bytes.len() as u32,
+            ],
+        );
+        put_u32s(&mut out, rows.iter().map(|r| r.geonameid));
+        put_u32s(&mut out, rows.iter().map(|r| r.name));
+        put_f64s(&mut out, rows.iter().map(|r| r.latitude));
+        put_f64s(&mut out, rows.iter().map(|r| r.longitude));
+        put_u32s(&mut out, rows.iter().map(|r| r.countrycode));
+        put_u32s(&mut out, rows.iter().map(|r| r.population));
+        put_u32s(&mut out, rows.iter().map(|r| r.timezone));
+        put_u32s(&mut out, rows.iter().map(|r| r.admin1code));
+        put_u32s(&mut out, offsets(|r| r.alternatenames.len()));
+        put_u32s(&mut out, alternatenames);
+        put_u32s(&mut out, offsets(|r| r.localized_names.len()));
+        put_u32s(&mut out, localized_names);
+        put_u32s(&mut out, by_name.iter().map(|&row| keys[row as usize]));
+        put_u32s(&mut out, by_name);
+        put_u32s(&mut out, self.starts);
+        out.extend_from_slice(&self.bytes);
+        out
+    }
+}
+
+/// Encode the records as a snapshot.
+pub fn write<'a, I>(records: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = Record<'a>>,
+{
+    let mut writer = Writer::default();
+    for r in records {
+        writer.push(r);
+    }
+    writer.finish()
+}
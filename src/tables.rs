@@ -0,0 +1,249 @@
+//! Country, continent and US state tables generated by `build.rs` as `static` arrays
+//! with `const fn` lookups. Lookups don't parse any JSON or allocate. `Geonamescache`
+//! builds its countries, continents and US states from these tables too.
+//!
+//! # Examples
+//! ```
+//! use geonamescache::tables::{continent_by_code, country_by_iso};
+//! let fr = country_by_iso("FR").unwrap();
+//! assert_eq!(fr.iso3, "FRA");
+//! assert_eq!(continent_by_code(fr.continentcode).unwrap().name, "Europe");
+//! ```
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::geometry::{BoundingBox, Point};
+use crate::geotypes::{AlternateName, Continent, Country, Timezone, UsState};
+
+/// A country record with the fields of [`crate::Country`], stored in a `static`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaticCountry {
+    pub geonameid: u32,
+    pub name: &'static str,
+    pub iso: &'static str,
+    pub iso3: &'static str,
+    pub isonumeric: u32,
+    pub fips: &'static str,
+    pub continentcode: &'static str,
+    pub capital: &'static str,
+    pub areakm2: u32,
+    pub population: u32,
+    pub tld: &'static str,
+    pub currencycode: &'static str,
+    pub currencyname: &'static str,
+    pub phone: &'static str,
+    pub languages: &'static str,
+    pub neighbours: &'static str,
+    /// From the country shapes or, without them, the bundled cities. `None` when
+    /// neither was available at build time.
+    pub bbox: Option<BoundingBox>,
+    pub centroid: Option<Point>,
+}
+
+/// A continent record with the fields of [`crate::Continent`], stored in a `static`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaticContinent {
+    pub geonameid: u32,
+    pub code: &'static str,
+    pub name: &'static str,
+    pub toponym_name: &'static str,
+    pub ascii_name: &'static str,
+    pub population: u32,
+    pub wikipedia_url: &'static str,
+    /// ISO codes of the continent's countries.
+    pub cc2: &'static [&'static str],
+    pub lat: &'static str,
+    pub lng: &'static str,
+    pub centroid: Point,
+    pub bbox: BoundingBox,
+    pub timezone: StaticTimezone,
+    pub alternate_names: &'static [StaticAlternateName],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaticTimezone {
+    pub gmt_offset: i8,
+    pub dst_offset: i8,
+    pub time_zone_id: &'static str,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaticAlternateName {
+    pub name: &'static str,
+    pub lang: &'static str,
+    pub is_preferred_name: bool,
+    pub is_short_name: bool,
+}
+
+/// A US state record with the fields of [`crate::UsState`], stored in a `static`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaticUsState {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub fips: &'static str,
+    pub geonameid: u32,
+}
+
+impl From<&StaticCountry> for Country {
+    fn from(c: &StaticCountry) -> Self {
+        Country {
+            geonameid: c.geonameid,
+            name: c.name.to_string(),
+            iso: c.iso.to_string(),
+            iso3: c.iso3.to_string(),
+            isonumeric: c.isonumeric,
+            fips: c.fips.to_string(),
+            continentcode: c.continentcode.to_string(),
+            capital: c.capital.to_string(),
+            areakm2: c.areakm2,
+            population: c.population,
+            tld: c.tld.to_string(),
+            currencycode: c.currencycode.to_string(),
+            currencyname: c.currencyname.to_string(),
+            phone: c.phone.to_string(),
+            languages: c.languages.to_string(),
+            neighbours: c.neighbours.to_string(),
+            bbox: c.bbox,
+            centroid: c.centroid,
+            localized_names: Vec::new(),
+        }
+    }
+}
+
+impl From<&StaticContinent> for Continent {
+    fn from(c: &StaticContinent) -> Self {
+        Continent {
+            lng: c.lng.to_string(),
+            lat: c.lat.to_string(),
+            geoname_id: c.geonameid,
+            timezone: Timezone {
+                gmt_offset: c.timezone.gmt_offset,
+                dst_offset: c.timezone.dst_offset,
+                time_zone_id: c.timezone.time_zone_id.to_string(),
+            },
+            toponym_name: c.toponym_name.to_string(),
+            ascii_name: c.ascii_name.to_string(),
+            name: c.name.to_string(),
+            continent_code: c.code.to_string(),
+            population: c.population,
+            wikipedia_url: c.wikipedia_url.to_string(),
+            alternate_names: c
+                .alternate_names
+                .iter()
+                .map(|n| AlternateName {
+                    name: n.name.to_string(),
+                    lang: n.lang.to_string(),
+                    is_preferred_name: n.is_preferred_name,
+                    is_short_name: n.is_short_name,
+                })
+                .collect(),
+            cc2: (!c.cc2.is_empty()).then(|| c.cc2.join(",")),
+            bbox: c.bbox,
+            centroid: c.centroid,
+        }
+    }
+}
+
+impl From<&StaticUsState> for UsState {
+    fn from(s: &StaticUsState) -> Self {
+        UsState {
+            code: s.code.to_string(),
+            name: s.name.to_string(),
+            fips: s.fips.to_string(),
+            geonameid: s.geonameid,
+        }
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/tables.rs"));
+
+/// Look up a country by its ISO 3166 alpha-2 code, e.g. `"FR"`. Case-sensitive.
+pub const fn country_by_iso(iso: &str) -> Option<&'static StaticCountry> {
+    match country_index(iso.as_bytes()) {
+        Some(i) => Some(&COUNTRIES[i]),
+        None => None,
+    }
+}
+
+/// Look up a continent by its code, e.g. `"EU"`. Case-sensitive.
+pub const fn continent_by_code(code: &str) -> Option<&'static StaticContinent> {
+    match continent_index(code.as_bytes()) {
+        Some(i) => Some(&CONTINENTS[i]),
+        None => None,
+    }
+}
+
+/// Look up a US state by its postal code, e.g. `"OH"`. Case-sensitive.
+pub const fn us_state_by_code(code: &str) -> Option<&'static StaticUsState> {
+    match us_state_index(code.as_bytes()) {
+        Some(i) => Some(&US_STATES[i]),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_country_by_iso() {
+        let de = country_by_iso("DE").unwrap();
+        assert_eq!(de.name, "Germany");
+        assert_eq!(de.iso3, "DEU");
+        assert_eq!(de.isonumeric, 276);
+        assert_eq!(country_by_iso("de"), None);
+        assert_eq!(country_by_iso("XX"), None);
+    }
+
+    #[test]
+    fn test_const_lookup() {
+        const FR: Option<&StaticCountry> = country_by_iso("FR");
+        assert_eq!(FR.unwrap().iso3, "FRA");
+    }
+
+    #[test]
+    fn test_tables_match_datasets() {
+        let countries: HashMap<String, Country> =
+            serde_json::from_str(include_str!("countries.json")).unwrap();
+        let continents: HashMap<String, Continent> =
+            serde_json::from_str(include_str!("continents.json")).unwrap();
+        assert_eq!(COUNTRIES.len(), countries.len());
+        assert_eq!(CONTINENTS.len(), continents.len());
+        assert_eq!(US_STATES.len(), 51);
+
+        for country in countries.values() {
+            let mut c = Country::from(country_by_iso(&country.iso).unwrap());
+            // Geometry missing from the dataset is filled in by `build.rs`.
+            if country.bbox.is_none() {
+                c.bbox = None;
+                c.centroid = None;
+            }
+            assert_eq!(&c, country);
+        }
+        for continent in continents.values() {
+            let c = continent_by_code(&continent.continent_code).unwrap();
+            let expected = Continent {
+                centroid: c.centroid,
+                ..continent.clone()
+            };
+            assert_eq!(Continent::from(c), expected);
+            assert_eq!(c.centroid.lat.to_string(), continent.lat);
+        }
+    }
+
+    #[test]
+    fn test_continent_by_code() {
+        let eu = continent_by_code("EU").unwrap();
+        assert_eq!(eu.name, "Europe");
+        assert!(eu.cc2.contains(&"FR"));
+    }
+
+    #[test]
+    fn test_us_state_by_code() {
+        let oh = us_state_by_code("OH").unwrap();
+        assert_eq!(oh.name, "Ohio");
+        assert_eq!(oh.fips, "39");
+    }
+}
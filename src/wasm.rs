@@ -0,0 +1,150 @@
+//! WebAssembly bindings, enabled with the `wasm` feature and packaged by
+//! `bindings/wasm`.
+//!
+//! Records are returned as plain JS objects with the same fields as the Rust types,
+//! and missing records as `null`. To keep the binary small, build without the
+//! `embedded-cities` feature and load the cities at runtime with
+//! [`WasmGeonamescache::with_cities_json`] or [`WasmGeonamescache::with_cities_snapshot`].
+//! A cache created with `new GeonamesCache()` then has countries but no cities, and
+//! its city lookups throw.
+use std::collections::HashMap;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::geometry::is_valid_coordinate;
+use crate::mappers::CountryMapper;
+use crate::{City, CompactCities, Geonamescache};
+
+fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = GeonamesCache)]
+pub struct WasmGeonamescache {
+    gc: Geonamescache,
+    /// False for a cache without any city dataset.
+    has_cities: bool,
+}
+
+impl WasmGeonamescache {
+    /// The cache, or an error if it has no cities to look up.
+    fn cities(&self) -> Result<&Geonamescache, JsError> {
+        if !self.has_cities {
+            return Err(JsError::new(
+                "no cities loaded, create the cache with GeonamesCache.withCitiesJson \
+                 or GeonamesCache.withCitiesSnapshot",
+            ));
+        }
+        Ok(&self.gc)
+    }
+}
+
+#[wasm_bindgen(js_class = GeonamesCache)]
+impl WasmGeonamescache {
+    /// A cache using the bundled datasets. Without the `embedded-cities` feature it has
+    /// no cities, see [`WasmGeonamescache::with_cities_json`].
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmGeonamescache {
+        WasmGeonamescache {
+            gc: Geonamescache::new(),
+            has_cities: cfg!(feature = "embedded-cities"),
+        }
+    }
+
+    /// A cache whose cities are parsed from the contents of a `cities.json` file.
+    #[wasm_bindgen(js_name = withCitiesJson)]
+    pub fn with_cities_json(json: &str) -> Result<WasmGeonamescache, JsError> {
+        let cities: HashMap<String, City> = serde_json::from_str(json)?;
+        Ok(WasmGeonamescache {
+            gc: Geonamescache::with_cities(cities),
+            has_cities: true,
+        })
+    }
+
+    /// A cache whose cities are read from a snapshot, see [`crate::snapshot`].
+    #[wasm_bindgen(js_name = withCitiesSnapshot)]
+    pub fn with_cities_snapshot(bytes: &[u8]) -> Result<WasmGeonamescache, JsError> {
+        let cities = CompactCities::from_bytes(bytes)?
+            .iter()
+            .map(|c| (c.geonameid().to_string(), c.to_city()))
+            .collect();
+        Ok(WasmGeonamescache {
+            gc: Geonamescache::with_cities(cities),
+            has_cities: true,
+        })
+    }
+
+    /// All countries, keyed by ISO code.
+    #[wasm_bindgen(js_name = getCountries)]
+    pub fn get_countries(&self) -> Result<JsValue, JsError> {
+        to_js(self.gc.get_countries())
+    }
+
+    /// The country with the given ISO code, e.g. `"FR"`.
+    #[wasm_bindgen(js_name = getCountry)]
+    pub fn get_country(&self, iso: &str) -> Result<JsValue, JsError> {
+        to_js(&self.gc.get_countries().get(iso))
+    }
+
+    /// See [`Geonamescache::resolve_country`].
+    #[wasm_bindgen(js_name = resolveCountry)]
+    pub fn resolve_country(&self, query: &str) -> Result<JsValue, JsError> {
+        to_js(&self.gc.resolve_country(query))
+    }
+
+    #[wasm_bindgen(js_name = getCitiesByName)]
+    pub fn get_cities_by_name(&self, name: &str) -> Result<JsValue, JsError> {
+        let cities = self.cities()?.get_cities_by_name(name);
+        to_js(&cities.values().collect::<Vec<&City>>())
+    }
+
+    #[wasm_bindgen(js_name = searchCities)]
+    pub fn search_cities(&self, query: &str) -> Result<JsValue, JsError> {
+        to_js(&self.cities()?.search_cities(query))
+    }
+
+    /// The closest city, or `null` if there are no cities. Throws for coordinates that
+    /// aren't finite or are outside ±90 latitude and ±180 longitude.
+    #[wasm_bindgen(js_name = nearestCity)]
+    pub fn nearest_city(&self, lat: f64, lon: f64) -> Result<JsValue, JsError> {
+        let gc = self.cities()?;
+        if !is_valid_coordinate(lat, lon) {
+            return Err(JsError::new(&format!(
+                "invalid coordinates {}, {}: expected a latitude between -90 and 90 and \
+                 a longitude between -180 and 180",
+                lat, lon
+            )));
+        }
+        to_js(&gc.nearest_city(lat, lon))
+    }
+
+    /// A mapper between two country fields given by name, e.g. `"name"` to `"iso3"`.
+    #[wasm_bindgen(js_name = countryMapper)]
+    pub fn country_mapper(&self, from: &str, to: &str) -> Result<WasmCountryMapper, JsError> {
+        Ok(WasmCountryMapper {
+            mapper: self.gc.country_field_mapper(from, to)?,
+        })
+    }
+}
+
+impl Default for WasmGeonamescache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_name = CountryMapper)]
+pub struct WasmCountryMapper {
+    mapper: CountryMapper<'static>,
+}
+
+#[wasm_bindgen(js_class = CountryMapper)]
+impl WasmCountryMapper {
+    /// The value mapped to `key` (case-insensitive), or `undefined`.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.mapper.get(key).map(str::to_string)
+    }
+}